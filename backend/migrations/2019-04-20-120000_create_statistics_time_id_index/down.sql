@@ -0,0 +1 @@
+DROP INDEX statistics@statistics_time_id;
@@ -0,0 +1 @@
+CREATE INDEX statistics_time_id ON statistics (time, id);
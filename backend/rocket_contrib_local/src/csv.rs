@@ -1,14 +1,26 @@
 //! A module providing a Csv wrapper that implements the Rocket Responder
-//! traits.
+//! traits, and the Rocket FromData traits for types deserialized from CSV.
 
 use rocket::{
+    data::{self, FromDataSimple},
     http::{ContentType, Status},
-    response::Responder,
-    Request, Response,
+    response::{status::BadRequest, Responder},
+    Data, Outcome, Request, Response,
 };
-use std::io::Cursor;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fmt,
+    io::{self, Cursor, Read},
+};
+
+/// The default limit for a CSV request body is 1MB.
+const LIMIT: u64 = 1 << 20;
 
 /// A wrapper for a type represented as CSV.
+///
+/// As a data guard `Csv<T>` expects a CSV body with a header row and exactly
+/// one record, while `Csv<Vec<T>>` accepts any number of records. The body is
+/// limited by the `csv` limit, see the Rocket documentation on limits.
 pub struct Csv<T>(pub T, pub fn(&T) -> String);
 
 impl<T> Csv<T> {
@@ -32,3 +44,332 @@ impl<T> Responder<'static> for Csv<T> {
             .ok()
     }
 }
+
+/// A type that is represented by a single CSV record. Implementing this for a
+/// type allows both `Csv<T>` and `Csv<Vec<T>>` to be used as data guards.
+pub trait CsvRecord: DeserializeOwned + Serialize {}
+
+impl<T: CsvRecord> FromDataSimple for Csv<T> {
+    type Error = CsvError;
+
+    fn from_data(request: &Request, data: Data) -> data::Outcome<Self, Self::Error> {
+        match read_records::<T>(request, data).and_then(single_record) {
+            Ok(record) => Outcome::Success(Csv(record, serialize_record)),
+            Err(error) => Outcome::Failure((error.status(), error)),
+        }
+    }
+}
+
+impl<T: CsvRecord> FromDataSimple for Csv<Vec<T>> {
+    type Error = CsvError;
+
+    fn from_data(request: &Request, data: Data) -> data::Outcome<Self, Self::Error> {
+        match read_records::<T>(request, data) {
+            Ok(records) => Outcome::Success(Csv(records, serialize_records)),
+            Err(error) => Outcome::Failure((error.status(), error)),
+        }
+    }
+}
+
+/// Read the records from the CSV body of the `request`, limited by its `csv`
+/// limit.
+fn read_records<T: CsvRecord>(request: &Request, data: Data) -> Result<Vec<T>, CsvError> {
+    let size_limit = request.limits().get("csv").unwrap_or(LIMIT);
+    parse_records(data.open(), size_limit)
+}
+
+/// Parse the records from a CSV `body` with a header row. A body over the
+/// `size_limit` is rejected rather than cut short, as a truncated body may
+/// still parse into fewer or altered records.
+fn parse_records<T: CsvRecord, R: Read>(body: R, size_limit: u64) -> Result<Vec<T>, CsvError> {
+    let mut bytes = Vec::new();
+    body.take(size_limit.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .map_err(CsvError::Io)?;
+    if bytes.len() as u64 > size_limit {
+        return Err(CsvError::TooLarge(size_limit));
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(bytes.as_slice());
+
+    let headers = reader
+        .headers()
+        .map_err(|error| CsvError::new(error, None))?
+        .clone();
+
+    // The records are read one at a time rather than deserialized by the
+    // reader, as trimming a record discards its position, which is restored
+    // for an error to report its line.
+    let error = |error| CsvError::new(error, Some(&headers));
+    let mut records = Vec::new();
+    let mut record = csv::StringRecord::new();
+    loop {
+        let position = reader.position().clone();
+        if !reader.read_record(&mut record).map_err(error)? {
+            return Ok(records);
+        }
+        record.set_position(Some(position));
+        records.push(record.deserialize(Some(&headers)).map_err(error)?);
+    }
+}
+
+/// The only record of the `records`, an error if there is not exactly one.
+fn single_record<T>(mut records: Vec<T>) -> Result<T, CsvError> {
+    if records.len() == 1 {
+        Ok(records.remove(0))
+    } else {
+        Err(CsvError::RecordCount(records.len()))
+    }
+}
+
+/// Serialize a single record, including the header row.
+fn serialize_record<T: Serialize>(record: &T) -> String {
+    let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+    wtr.serialize(record).unwrap();
+    String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+}
+
+/// Serialize a set of records, including the header row.
+fn serialize_records<T: Serialize>(records: &Vec<T>) -> String {
+    let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+    for record in records {
+        wtr.serialize(record).unwrap();
+    }
+    String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+}
+
+/// An error returned by the `Csv` data guard when the incoming data fails to
+/// deserialize as CSV. As a responder it results in a 400 response describing
+/// the error, or a 413 response if the data is too large.
+#[derive(Debug)]
+pub enum CsvError {
+    /// An I/O error occurred while reading the incoming request data.
+    Io(io::Error),
+
+    /// The incoming data was not valid CSV or a record could not be
+    /// deserialized into the requested type.
+    Parse {
+        /// The line on which the error occurred, if known.
+        line: Option<u64>,
+
+        /// The column in which the error occurred, if known.
+        column: Option<String>,
+
+        /// A description of the error.
+        message: String,
+    },
+
+    /// The incoming data did not contain exactly one record, the number of
+    /// records found is included.
+    RecordCount(usize),
+
+    /// The incoming data exceeded the size limit, which is included.
+    TooLarge(u64),
+}
+
+impl CsvError {
+    /// Create a `CsvError` from an error of the `csv` crate. The headers are
+    /// used to name the column in which a deserialization error occurred.
+    fn new(error: csv::Error, headers: Option<&csv::StringRecord>) -> Self {
+        match error.into_kind() {
+            csv::ErrorKind::Io(error) => CsvError::Io(error),
+            csv::ErrorKind::Utf8 { pos, err } => CsvError::Parse {
+                line: pos.map(|pos| pos.line()),
+                column: Some(column_name(headers, err.field() as u64)),
+                message: err.to_string(),
+            },
+            csv::ErrorKind::UnequalLengths {
+                pos,
+                expected_len,
+                len,
+            } => CsvError::Parse {
+                line: pos.map(|pos| pos.line()),
+                column: None,
+                message: format!("expected {} fields, found {}", expected_len, len),
+            },
+            csv::ErrorKind::Deserialize { pos, err } => CsvError::Parse {
+                line: pos.map(|pos| pos.line()),
+                column: err.field().map(|field| column_name(headers, field)),
+                message: err.kind().to_string(),
+            },
+            kind => CsvError::Parse {
+                line: None,
+                column: None,
+                message: format!("{:?}", kind),
+            },
+        }
+    }
+
+    /// The status of the response to the error, 413 if the body is too large
+    /// and 400 otherwise.
+    pub fn status(&self) -> Status {
+        match self {
+            CsvError::TooLarge(_) => Status::PayloadTooLarge,
+            _ => Status::BadRequest,
+        }
+    }
+}
+
+/// Name a column by the header at the `field` index, falling back to its
+/// 1-based position.
+fn column_name(headers: Option<&csv::StringRecord>, field: u64) -> String {
+    match headers.and_then(|headers| headers.get(field as usize)) {
+        Some(header) => header.to_owned(),
+        None => format!("{}", field + 1),
+    }
+}
+
+impl fmt::Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CsvError::Io(error) => write!(f, "could not read the CSV body: {}", error),
+            CsvError::Parse {
+                line,
+                column,
+                message,
+            } => {
+                if let Some(line) = line {
+                    write!(f, "line {}: ", line)?;
+                }
+                if let Some(column) = column {
+                    write!(f, "column `{}`: ", column)?;
+                }
+                write!(f, "{}", message)
+            }
+            CsvError::RecordCount(count) => {
+                write!(f, "expected exactly 1 record, found {}", count)
+            }
+            CsvError::TooLarge(limit) => {
+                write!(f, "the CSV body exceeds the limit of {} bytes", limit)
+            }
+        }
+    }
+}
+
+impl<'r> Responder<'r> for CsvError {
+    fn respond_to(self, request: &Request) -> Result<Response<'r>, Status> {
+        match self.status() {
+            Status::BadRequest => BadRequest(Some(self.to_string())).respond_to(request),
+            status => Response::build_from(self.to_string().respond_to(request)?)
+                .status(status)
+                .ok(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Record {
+        name: String,
+        count: i64,
+    }
+
+    impl CsvRecord for Record {}
+
+    fn parse(body: &str) -> Result<Vec<Record>, CsvError> {
+        parse_records(body.as_bytes(), LIMIT)
+    }
+
+    #[test]
+    fn records_are_parsed_by_their_headers() {
+        let records = parse("count,name\n1, a \n2,b\n").unwrap();
+
+        assert_eq!(
+            records,
+            vec![
+                Record {
+                    name: String::from("a"),
+                    count: 1,
+                },
+                Record {
+                    name: String::from("b"),
+                    count: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn body_over_the_limit_is_too_large() {
+        let body = "name,count\na,1\n";
+
+        let error = parse_records::<Record, _>(body.as_bytes(), body.len() as u64 - 1)
+            .err()
+            .unwrap();
+
+        match error {
+            CsvError::TooLarge(limit) => assert_eq!(limit, body.len() as u64 - 1),
+            error => panic!("unexpected error {:?}", error),
+        }
+        assert_eq!(error.status(), Status::PayloadTooLarge);
+    }
+
+    #[test]
+    fn body_at_the_limit_is_accepted() {
+        let body = "name,count\na,1\n";
+
+        let records = parse_records::<Record, _>(body.as_bytes(), body.len() as u64).unwrap();
+
+        assert_eq!(records.len(), 1);
+    }
+
+    #[test]
+    fn empty_body_has_no_records() {
+        assert!(parse("").unwrap().is_empty());
+        assert!(parse("name,count\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn single_record_of_an_empty_body_is_a_bad_request() {
+        let error = single_record(parse("").unwrap()).err().unwrap();
+
+        match error {
+            CsvError::RecordCount(count) => assert_eq!(count, 0),
+            error => panic!("unexpected error {:?}", error),
+        }
+        assert_eq!(error.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn single_record_rejects_several_records() {
+        let records = parse("name,count\na,1\nb,2\n").unwrap();
+
+        let error = single_record(records).err().unwrap();
+
+        assert_eq!(error.to_string(), "expected exactly 1 record, found 2");
+    }
+
+    #[test]
+    fn deserialization_error_reports_its_line_and_column() {
+        let error = parse("name,count\na,1\nb,many\n").err().unwrap();
+
+        match &error {
+            CsvError::Parse { line, column, .. } => {
+                assert_eq!(*line, Some(3));
+                assert_eq!(column.as_ref().map(String::as_str), Some("count"));
+            }
+            error => panic!("unexpected error {:?}", error),
+        }
+        assert!(error.to_string().starts_with("line 3: column `count`: "));
+        assert_eq!(error.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn unequal_lengths_report_their_line() {
+        let error = parse("name,count\na,1,2\n").err().unwrap();
+
+        match error {
+            CsvError::Parse { line, column, .. } => {
+                assert_eq!(line, Some(2));
+                assert_eq!(column, None);
+            }
+            error => panic!("unexpected error {:?}", error),
+        }
+    }
+}
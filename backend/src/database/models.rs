@@ -123,8 +123,9 @@ pub struct Statistics {
 
 /// A set of statistics with all the information needed for insertion. In this
 /// instance the `id` for the statistics is generated upon insertion, and the
-/// `flights_total` and `minutes_delayed_total` are derived values.
-#[derive(Insertable)]
+/// `flights_total` and `minutes_delayed_total` are derived values. The same
+/// information is used to replace an existing set of statistics.
+#[derive(AsChangeset, Insertable)]
 #[table_name = "statistics"]
 pub struct NewStatistics<'a> {
     /// See [Statistics::flights_cancelled].
@@ -0,0 +1,314 @@
+//! A module providing the detection of anomalous months in the statistics of
+//! each carrier at each airport, relative to the history of that pair.
+
+use super::series::{mean, median, standard_deviation};
+use super::views::Anomaly;
+
+use crate::database::schema::statistics;
+use crate::routes::parameters::{ParameterError, YearMonth};
+use crate::routes::statistics::filter::StatisticsFilter;
+use crate::routes::statistics::timeseries::SeriesMetric;
+use chrono::NaiveDate;
+use diesel::{dsl::sql, prelude::*, result::Error, sql_types::BigInt};
+use rocket::{http::RawStr, request::FromFormValue};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// The factor scaling the median absolute deviation to estimate the standard
+/// deviation of normally distributed values.
+const MAD_SCALE: f64 = 1.4826;
+
+/// The fewest months in the history of a pair for its months to be judged.
+const MIN_HISTORY: usize = 3;
+
+/// How unusual a month is measured, as given by the `method` parameter, either
+/// `zscore` or `mad`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Method {
+    /// The distance from the mean in standard deviations.
+    ZScore,
+
+    /// The distance from the median in scaled median absolute deviations,
+    /// which is robust to the anomalies themselves.
+    Mad,
+}
+
+impl Default for Method {
+    /// The median absolute deviation.
+    fn default() -> Self {
+        Method::Mad
+    }
+}
+
+impl<'v> FromFormValue<'v> for Method {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "zscore" => Ok(Method::ZScore),
+            "mad" => Ok(Method::Mad),
+            _ => Err(ParameterError::new(
+                "method",
+                format!("unknown method `{}`, expected zscore or mad", form_value),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Method::ZScore => write!(f, "zscore"),
+            Method::Mad => write!(f, "mad"),
+        }
+    }
+}
+
+impl Method {
+    /// The threshold used if none is given.
+    fn default_threshold(self) -> f64 {
+        match self {
+            Method::ZScore => 3.0,
+            Method::Mad => 3.5,
+        }
+    }
+
+    /// The expected value and the scale of the deviations of the `values`,
+    /// `None` if the values do not deviate.
+    fn center_and_scale(self, values: &[f64]) -> Option<(f64, f64)> {
+        let (center, scale) = match self {
+            Method::ZScore => (mean(values)?, standard_deviation(values)?),
+            Method::Mad => {
+                let center = median(values)?;
+                let deviations: Vec<f64> =
+                    values.iter().map(|value| (value - center).abs()).collect();
+                (center, MAD_SCALE * median(&deviations)?)
+            }
+        };
+        if scale > 0.0 {
+            Some((center, scale))
+        } else {
+            None
+        }
+    }
+}
+
+/// The parameters of the anomaly detection, taken from the query parameters
+/// of a request.
+pub struct AnomalyParameters {
+    /// The column judged.
+    pub metric: SeriesMetric,
+
+    /// How unusual a month is measured.
+    pub method: Method,
+
+    /// The score beyond which a month is anomalous.
+    pub threshold: f64,
+}
+
+impl AnomalyParameters {
+    /// Check the `metric`, `method` and `threshold` parameters of a request,
+    /// the threshold must be a positive, finite number.
+    pub fn check(
+        metric: Option<Result<SeriesMetric, ParameterError>>,
+        method: Option<Result<Method, ParameterError>>,
+        threshold: Option<Result<f64, &RawStr>>,
+    ) -> Result<Self, ParameterError> {
+        let method = method.transpose()?.unwrap_or_default();
+        let threshold = match threshold {
+            Some(Ok(threshold)) => threshold,
+            Some(Err(threshold)) => {
+                return Err(ParameterError::new(
+                    "threshold",
+                    format!("`{}` is not a number", threshold),
+                ))
+            }
+            None => method.default_threshold(),
+        };
+        if threshold <= 0.0 || !threshold.is_finite() {
+            return Err(ParameterError::new(
+                "threshold",
+                "the threshold must be a positive number",
+            ));
+        }
+
+        Ok(AnomalyParameters {
+            metric: metric.transpose()?.unwrap_or_default(),
+            method,
+            threshold,
+        })
+    }
+
+    /// Format the parameters as the query parameters of a URI.
+    pub fn to_query(&self) -> String {
+        format!(
+            "metric={}&method={}&threshold={}",
+            self.metric, self.method, self.threshold
+        )
+    }
+}
+
+/// Find the months of the statistics matching the `filter` where the metric of
+/// a carrier at an airport is unusual relative to the history of that pair.
+/// The expected value and scale of a pair are taken from all of its months,
+/// whatever the period of the filter, so that a short period is judged against
+/// the whole history. The anomalies are ordered by carrier, airport and time.
+pub fn detect_anomalies(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    parameters: &AnomalyParameters,
+) -> Result<Vec<Anomaly>, Error> {
+    let flagged: HashSet<i64> = match filter.query(conn)? {
+        Some(query) => query
+            .select(statistics::id)
+            .load(conn)?
+            .into_iter()
+            .collect(),
+        None => return Ok(Vec::new()),
+    };
+    if flagged.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Only the carrier and airport of the filter restrict the history.
+    let history_filter = StatisticsFilter {
+        carrier: filter.carrier.clone(),
+        airport: filter.airport.clone(),
+        year: None,
+        month: None,
+        from: None,
+        to: None,
+    };
+    let rows = match history_filter.query(conn)? {
+        Some(query) => query
+            .select((
+                statistics::id,
+                statistics::carrier_id,
+                statistics::airport_id,
+                statistics::time,
+                sql::<BigInt>(&format!("statistics.{}", parameters.metric)),
+            ))
+            .order((
+                statistics::carrier_id,
+                statistics::airport_id,
+                statistics::time,
+            ))
+            .load::<(i64, i64, i64, NaiveDate, i64)>(conn)?,
+        None => Vec::new(),
+    };
+
+    let mut pairs = BTreeMap::new();
+    for (id, carrier_id, airport_id, time, value) in rows {
+        pairs
+            .entry((carrier_id, airport_id))
+            .or_insert_with(Vec::new)
+            .push((id, time, value));
+    }
+
+    let mut anomalies = Vec::new();
+    for ((carrier_id, airport_id), history) in pairs {
+        if history.len() < MIN_HISTORY {
+            continue;
+        }
+        let values: Vec<f64> = history.iter().map(|(_, _, value)| *value as f64).collect();
+        let (expected, scale) = match parameters.method.center_and_scale(&values) {
+            Some(center_and_scale) => center_and_scale,
+            None => continue,
+        };
+
+        for (id, time, value) in history {
+            if !flagged.contains(&id) {
+                continue;
+            }
+            let score = (value as f64 - expected) / scale;
+            if score.abs() > parameters.threshold {
+                anomalies.push(Anomaly {
+                    id,
+                    carrier_id,
+                    airport_id,
+                    month: YearMonth::from(time),
+                    observed: value,
+                    expected,
+                    lower: expected - parameters.threshold * scale,
+                    upper: expected + parameters.threshold * scale,
+                    score,
+                });
+            }
+        }
+    }
+
+    Ok(anomalies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn zscore_is_centered_on_the_mean_and_scaled_by_the_standard_deviation() {
+        let (center, scale) = Method::ZScore
+            .center_and_scale(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0])
+            .unwrap();
+        // The squared deviations from the mean 5 sum to 32, over 7 degrees of
+        // freedom.
+        assert_close(center, 5.0);
+        assert_close(scale, (32.0f64 / 7.0).sqrt());
+    }
+
+    #[test]
+    fn mad_is_centered_on_the_median_and_scaled_by_the_median_absolute_deviation() {
+        // The deviations from the median 3 are 2, 1, 0, 1 and 97, of median 1.
+        let (center, scale) = Method::Mad
+            .center_and_scale(&[1.0, 2.0, 3.0, 4.0, 100.0])
+            .unwrap();
+        assert_close(center, 3.0);
+        assert_close(scale, MAD_SCALE);
+    }
+
+    #[test]
+    fn mad_is_not_moved_by_an_outlier() {
+        let (center, scale) = Method::Mad
+            .center_and_scale(&[10.0, 11.0, 9.0, 10.0, 1000.0])
+            .unwrap();
+        assert_close(center, 10.0);
+        assert_close(scale, MAD_SCALE);
+    }
+
+    #[test]
+    fn constant_values_have_no_scale() {
+        assert!(Method::ZScore.center_and_scale(&[3.0; 5]).is_none());
+        assert!(Method::Mad.center_and_scale(&[3.0; 5]).is_none());
+        assert!(Method::Mad.center_and_scale(&[]).is_none());
+    }
+
+    #[test]
+    fn threshold_defaults_to_that_of_the_method() {
+        let parameters = AnomalyParameters::check(None, Some(Ok(Method::ZScore)), None).unwrap();
+        assert_close(parameters.threshold, 3.0);
+        let parameters = AnomalyParameters::check(None, None, None).unwrap();
+        assert_close(parameters.threshold, 3.5);
+    }
+
+    #[test]
+    fn threshold_must_be_a_positive_finite_number() {
+        for threshold in &[0.0, -1.0, std::f64::INFINITY, std::f64::NAN] {
+            let error = AnomalyParameters::check(None, None, Some(Ok(*threshold)))
+                .err()
+                .unwrap();
+            assert_eq!(error.parameter, "threshold");
+        }
+        let error = AnomalyParameters::check(None, None, Some(Err(RawStr::from_str("abc"))))
+            .err()
+            .unwrap();
+        assert_eq!(error.parameter, "threshold");
+    }
+}
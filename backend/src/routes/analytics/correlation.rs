@@ -0,0 +1,225 @@
+//! A module providing the Pearson and Spearman correlations between columns of
+//! the statistics, over the monthly statistics of each carrier at each airport.
+
+use super::series::mean;
+use super::views::Correlation;
+
+use crate::routes::parameters::ParameterError;
+use crate::routes::statistics::columns::{column_names, Column};
+use crate::routes::statistics::filter::StatisticsFilter;
+use diesel::{
+    dsl::sql,
+    prelude::*,
+    result::Error,
+    sql_types::{Array, BigInt},
+};
+use rocket::{http::RawStr, request::FromFormValue};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The columns correlated with each other as given by the `metrics` parameter,
+/// a comma separated list of at least 2 columns which may be summed, e.g.
+/// `metrics=flights_cancelled,minutes_delayed_weather`. The matrices follow
+/// the order given.
+#[derive(Clone, Debug)]
+pub struct CorrelationMetrics(Vec<Column>);
+
+impl<'v> FromFormValue<'v> for CorrelationMetrics {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let value = form_value
+            .url_decode()
+            .map_err(|_| ParameterError::new("metrics", "not valid UTF-8"))?;
+
+        let mut columns = Vec::new();
+        for name in value.split(',').map(str::trim) {
+            let column = match Column::from_name(name) {
+                Some(column) if column.is_aggregable() => column,
+                Some(_) => {
+                    return Err(ParameterError::new(
+                        "metrics",
+                        format!("column `{}` can not be correlated", name),
+                    ))
+                }
+                None => {
+                    return Err(ParameterError::new(
+                        "metrics",
+                        format!(
+                            "unknown column `{}`, expected one of {}",
+                            name,
+                            column_names()
+                        ),
+                    ))
+                }
+            };
+            if columns.contains(&column) {
+                return Err(ParameterError::new(
+                    "metrics",
+                    format!("column `{}` is given more than once", name),
+                ));
+            }
+            columns.push(column);
+        }
+
+        if columns.len() < 2 {
+            return Err(ParameterError::new(
+                "metrics",
+                "at least 2 columns are needed to correlate",
+            ));
+        }
+        Ok(CorrelationMetrics(columns))
+    }
+}
+
+impl fmt::Display for CorrelationMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = self.0.iter().map(|column| column.name()).collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+impl CorrelationMetrics {
+    /// Check the `metrics` parameter of a request, which is required.
+    pub fn check(
+        metrics: Option<Result<CorrelationMetrics, ParameterError>>,
+    ) -> Result<Self, ParameterError> {
+        metrics.unwrap_or_else(|| {
+            Err(ParameterError::new(
+                "metrics",
+                "the columns to correlate must be given",
+            ))
+        })
+    }
+
+    /// Format the metrics as a query parameter of a URI.
+    pub fn to_query(&self) -> String {
+        format!("metrics={}", self)
+    }
+}
+
+/// The Pearson correlation of the `xs` and `ys`, `None` if there are fewer than
+/// 2 values or either does not vary.
+fn pearson(xs: &[f64], ys: &[f64]) -> Option<f64> {
+    if xs.len() < 2 {
+        return None;
+    }
+
+    let (mean_x, mean_y) = (mean(xs)?, mean(ys)?);
+    let covariance: f64 = xs
+        .iter()
+        .zip(ys)
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance_x: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    let variance_y: f64 = ys.iter().map(|y| (y - mean_y).powi(2)).sum();
+    if variance_x > 0.0 && variance_y > 0.0 {
+        Some(covariance / (variance_x * variance_y).sqrt())
+    } else {
+        None
+    }
+}
+
+/// The ranks of the `values`, starting at 1, tied values sharing the average
+/// of their ranks.
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|a, b| {
+        values[*a]
+            .partial_cmp(&values[*b])
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end + 1) as f64 / 2.0;
+        for index in &order[start..end] {
+            ranks[*index] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// The matrix of the `correlation` of each pair of `columns`.
+fn matrix(
+    columns: &[Vec<f64>],
+    correlation: fn(&[f64], &[f64]) -> Option<f64>,
+) -> Vec<Vec<Option<f64>>> {
+    columns
+        .iter()
+        .map(|xs| columns.iter().map(|ys| correlation(xs, ys)).collect())
+        .collect()
+}
+
+/// Correlate the `metrics` over the statistics matching the `filter`, each set
+/// of statistics of a carrier at an airport in a month being an observation.
+/// The Spearman correlation is the Pearson correlation of the ranks.
+pub fn correlate(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    metrics: &CorrelationMetrics,
+) -> Result<Correlation, Error> {
+    let names: Vec<&str> = metrics.0.iter().map(|column| column.name()).collect();
+    let array = names
+        .iter()
+        .map(|name| format!("statistics.{}", name))
+        .collect::<Vec<String>>()
+        .join(", ");
+
+    let rows = match filter.query(conn)? {
+        Some(query) => query
+            .select(sql::<Array<BigInt>>(&format!("ARRAY[{}]", array)))
+            .load::<Vec<i64>>(conn)?,
+        None => Vec::new(),
+    };
+
+    let columns: Vec<Vec<f64>> = (0..names.len())
+        .map(|index| rows.iter().map(|row| row[index] as f64).collect())
+        .collect();
+    let ranked: Vec<Vec<f64>> = columns.iter().map(|values| ranks(values)).collect();
+
+    Ok(Correlation {
+        metrics: names.into_iter().map(String::from).collect(),
+        observations: rows.len(),
+        pearson: matrix(&columns, pearson),
+        spearman: matrix(&ranked, pearson),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_start_at_1() {
+        assert_eq!(ranks(&[30.0, 10.0, 20.0]), vec![3.0, 1.0, 2.0]);
+    }
+
+    #[test]
+    fn tied_ranks_are_averaged() {
+        assert_eq!(
+            ranks(&[10.0, 20.0, 10.0, 30.0, 20.0, 20.0]),
+            vec![1.5, 4.0, 1.5, 6.0, 4.0, 4.0]
+        );
+    }
+
+    #[test]
+    fn pearson_of_lines() {
+        let xs = [1.0, 2.0, 3.0, 4.0];
+
+        assert!((pearson(&xs, &[3.0, 5.0, 7.0, 9.0]).unwrap() - 1.0).abs() < 1e-9);
+        assert!((pearson(&xs, &[4.0, 3.0, 2.0, 1.0]).unwrap() + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn pearson_of_a_constant_is_unknown() {
+        assert_eq!(pearson(&[1.0, 2.0, 3.0], &[5.0, 5.0, 5.0]), None);
+        assert_eq!(pearson(&[1.0], &[5.0]), None);
+    }
+}
@@ -0,0 +1,310 @@
+//! A module providing the classical seasonal decomposition of a monthly series
+//! into its trend, seasonal component and residual.
+
+use super::series::{mean, Series};
+use super::views::{Decomposition, DecompositionPoint, SeasonalIndex};
+
+use crate::routes::parameters::ParameterError;
+use rocket::{http::RawStr, request::FromFormValue};
+use std::fmt;
+
+/// The number of months of a season, a year.
+pub const PERIOD: usize = 12;
+
+/// How the components of a series combine, as given by the `model` parameter,
+/// either `additive` or `multiplicative`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Model {
+    /// The value is the sum of the trend, the seasonal component and the
+    /// residual.
+    Additive,
+
+    /// The value is the product of the trend, the seasonal component and the
+    /// residual, for seasonal swings growing with the trend.
+    Multiplicative,
+}
+
+impl Default for Model {
+    /// The additive model.
+    fn default() -> Self {
+        Model::Additive
+    }
+}
+
+impl<'v> FromFormValue<'v> for Model {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "additive" => Ok(Model::Additive),
+            "multiplicative" => Ok(Model::Multiplicative),
+            _ => Err(ParameterError::new(
+                "model",
+                format!(
+                    "unknown model `{}`, expected additive or multiplicative",
+                    form_value
+                ),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Model::Additive => write!(f, "additive"),
+            Model::Multiplicative => write!(f, "multiplicative"),
+        }
+    }
+}
+
+impl Model {
+    /// Remove the `component` from the `value`.
+    fn remove(self, value: f64, component: f64) -> Option<f64> {
+        match self {
+            Model::Additive => Some(value - component),
+            Model::Multiplicative if component != 0.0 => Some(value / component),
+            Model::Multiplicative => None,
+        }
+    }
+
+    /// The neutral value of a component, which leaves the others unchanged.
+    fn neutral(self) -> f64 {
+        match self {
+            Model::Additive => 0.0,
+            Model::Multiplicative => 1.0,
+        }
+    }
+}
+
+/// The trend of the `series` as the centred moving average over a period,
+/// weighting the months at both ends by half. It is `None` for the half period
+/// at each end of the series, and wherever a month in the window is missing.
+pub fn trend(series: &Series) -> Vec<Option<f64>> {
+    let half = PERIOD / 2;
+    (0..series.len())
+        .map(|index| {
+            if index < half || index + half >= series.len() {
+                return None;
+            }
+            let window = &series[index - half..=index + half];
+            let mut sum = 0.0;
+            for (position, (_, value)) in window.iter().enumerate() {
+                let weight = if position == 0 || position == PERIOD {
+                    0.5
+                } else {
+                    1.0
+                };
+                sum += weight * (*value)?;
+            }
+            Some(sum / PERIOD as f64)
+        })
+        .collect()
+}
+
+/// The seasonal indices of the `series`, by month of the year starting with
+/// January, as the mean of the detrended values of the month. The indices are
+/// normalised to sum to zero in the additive model and to average one in the
+/// multiplicative model. An index is `None` if the month is never detrended.
+pub fn seasonal_indices(series: &Series, trend: &[Option<f64>], model: Model) -> Vec<Option<f64>> {
+    let mut detrended = vec![Vec::new(); PERIOD];
+    for ((month, value), trend) in series.iter().zip(trend) {
+        if let (Some(value), Some(trend)) = (value, trend) {
+            if let Some(detrended_value) = model.remove(*value, *trend) {
+                detrended[month.month as usize - 1].push(detrended_value);
+            }
+        }
+    }
+
+    let indices: Vec<Option<f64>> = detrended.iter().map(|values| mean(values)).collect();
+    let present: Vec<f64> = indices.iter().filter_map(|index| *index).collect();
+    let adjustment = mean(&present).unwrap_or_else(|| model.neutral());
+    indices
+        .into_iter()
+        .map(|index| {
+            index.and_then(|index| match model {
+                Model::Additive => Some(index - adjustment),
+                Model::Multiplicative => model.remove(index, adjustment),
+            })
+        })
+        .collect()
+}
+
+/// The slope of the least squares line through the known values of the
+/// `trend`, per year. `None` if there are fewer than 2 values.
+fn slope_per_year(trend: &[Option<f64>]) -> Option<f64> {
+    let points: Vec<(f64, f64)> = trend
+        .iter()
+        .enumerate()
+        .filter_map(|(index, value)| value.map(|value| (index as f64, value)))
+        .collect();
+    if points.len() < 2 {
+        return None;
+    }
+
+    let xs: Vec<f64> = points.iter().map(|(x, _)| *x).collect();
+    let ys: Vec<f64> = points.iter().map(|(_, y)| *y).collect();
+    let (mean_x, mean_y) = (mean(&xs)?, mean(&ys)?);
+    let covariance: f64 = points
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let variance: f64 = xs.iter().map(|x| (x - mean_x).powi(2)).sum();
+    Some(covariance / variance * PERIOD as f64)
+}
+
+/// Decompose the monthly `series` of the `metric` into its trend, seasonal
+/// component and residual according to the `model`. The trend is not known
+/// for the first and last 6 months of the series, so neither is the residual.
+pub fn decompose(series: &Series, metric: String, model: Model) -> Decomposition {
+    let trend = trend(series);
+    let indices = seasonal_indices(series, &trend, model);
+
+    let points = series
+        .iter()
+        .zip(&trend)
+        .map(|((month, value), trend)| {
+            let seasonal = indices[month.month as usize - 1];
+            let residual = match (value, trend, seasonal) {
+                (Some(value), Some(trend), Some(seasonal)) => model
+                    .remove(*value, *trend)
+                    .and_then(|detrended| model.remove(detrended, seasonal)),
+                _ => None,
+            };
+            DecompositionPoint {
+                month: *month,
+                value: *value,
+                trend: *trend,
+                seasonal,
+                residual,
+            }
+        })
+        .collect();
+
+    Decomposition {
+        metric,
+        model: model.to_string(),
+        trend_slope_per_year: slope_per_year(&trend),
+        seasonal_indices: indices
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| SeasonalIndex {
+                month: index as u32 + 1,
+                value,
+            })
+            .collect(),
+        points,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::routes::parameters::YearMonth;
+
+    /// The monthly series of the `values`, starting in January 2010.
+    fn series(values: &[Option<f64>]) -> Series {
+        values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                (
+                    YearMonth {
+                        year: 2010 + (index / PERIOD) as i32,
+                        month: (index % PERIOD) as u32 + 1,
+                    },
+                    *value,
+                )
+            })
+            .collect()
+    }
+
+    fn assert_close(actual: &[Option<f64>], expected: &[Option<f64>]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            match (actual, expected) {
+                (Some(actual), Some(expected)) => assert!(
+                    (actual - expected).abs() < 1e-9,
+                    "{:?} != {:?}",
+                    actual,
+                    expected
+                ),
+                _ => assert_eq!(actual, expected),
+            }
+        }
+    }
+
+    #[test]
+    fn trend_of_a_constant_is_the_constant() {
+        let trend = trend(&series(&[Some(5.0); 2 * PERIOD]));
+
+        let mut expected = vec![None; 6];
+        expected.extend(vec![Some(5.0); PERIOD]);
+        expected.extend(vec![None; 6]);
+        assert_close(&trend, &expected);
+    }
+
+    #[test]
+    fn trend_of_a_line_is_the_line() {
+        let values: Vec<Option<f64>> = (0..2 * PERIOD).map(|x| Some(3.0 * x as f64)).collect();
+
+        let trend = trend(&series(&values));
+
+        assert_close(&trend[6..18], &values[6..18]);
+    }
+
+    #[test]
+    fn trend_is_unknown_where_a_month_of_the_window_is_missing() {
+        let mut values = vec![Some(5.0); 2 * PERIOD];
+        values[0] = None;
+
+        let trend = trend(&series(&values));
+
+        assert_eq!(trend[6], None);
+        assert_close(&trend[7..18], &[Some(5.0); 11]);
+    }
+
+    #[test]
+    fn additive_seasonal_indices_are_the_seasonal_offsets() {
+        let offsets = [
+            -6.0, -4.0, -2.0, 0.0, 2.0, 4.0, 6.0, 4.0, 2.0, 0.0, -2.0, -4.0,
+        ];
+        let values: Vec<Option<f64>> = offsets
+            .iter()
+            .cycle()
+            .take(3 * PERIOD)
+            .map(|offset| Some(100.0 + offset))
+            .collect();
+        let series = series(&values);
+
+        let indices = seasonal_indices(&series, &trend(&series), Model::Additive);
+
+        let expected: Vec<Option<f64>> = offsets.iter().map(|offset| Some(*offset)).collect();
+        assert_close(&indices, &expected);
+    }
+
+    #[test]
+    fn multiplicative_seasonal_indices_are_the_seasonal_factors() {
+        let factors = [0.7, 0.8, 0.9, 1.0, 1.1, 1.2, 1.3, 1.2, 1.1, 1.0, 0.9, 0.8];
+        let values: Vec<Option<f64>> = factors
+            .iter()
+            .cycle()
+            .take(3 * PERIOD)
+            .map(|factor| Some(100.0 * factor))
+            .collect();
+        let series = series(&values);
+
+        let indices = seasonal_indices(&series, &trend(&series), Model::Multiplicative);
+
+        let expected: Vec<Option<f64>> = factors.iter().map(|factor| Some(*factor)).collect();
+        assert_close(&indices, &expected);
+    }
+
+    #[test]
+    fn slope_per_year_of_a_line() {
+        let trend: Vec<Option<f64>> = (0..PERIOD).map(|x| Some(2.0 * x as f64)).collect();
+
+        assert_close(&[slope_per_year(&trend)], &[Some(24.0)]);
+        assert_eq!(slope_per_year(&trend[..1]), None);
+    }
+}
@@ -0,0 +1,256 @@
+//! A module providing the composition of the minutes delayed and the delays of
+//! each carrier or airport by cause, and its change from a baseline period.
+
+use super::views::{DelayCause, DelayCauses};
+
+use crate::routes::parameters::{ParameterError, YearMonth};
+use crate::routes::statistics::filter::{filtered_join, sum_int8, GroupBy, StatisticsFilter};
+use diesel::{prelude::*, result::Error};
+use std::collections::HashMap;
+
+/// The causes of the delays, the suffix of their columns, and whether they are
+/// caused by the carrier itself rather than by the system.
+const CAUSES: &[(&str, bool)] = &[
+    ("carrier", true),
+    ("late_aircraft", true),
+    ("national_aviation_system", false),
+    ("security", false),
+    ("weather", false),
+];
+
+/// The parameters of the delay causes, taken from the query parameters of a
+/// request.
+pub struct DelayCauseParameters {
+    /// What the delays are grouped by.
+    pub group_by: GroupBy,
+
+    /// The first month of the baseline period, if any.
+    pub baseline_from: Option<YearMonth>,
+
+    /// The last month of the baseline period, if any.
+    pub baseline_to: Option<YearMonth>,
+}
+
+impl DelayCauseParameters {
+    /// Check the `group_by`, `baseline_from` and `baseline_to` parameters of a
+    /// request, the baseline period must not end before it starts.
+    pub fn check(
+        group_by: Option<Result<GroupBy, ParameterError>>,
+        baseline_from: Option<Result<YearMonth, ParameterError>>,
+        baseline_to: Option<Result<YearMonth, ParameterError>>,
+    ) -> Result<Self, ParameterError> {
+        let baseline_from = baseline_from
+            .transpose()
+            .map_err(|error| ParameterError::new("baseline_from", error.message))?;
+        let baseline_to = baseline_to
+            .transpose()
+            .map_err(|error| ParameterError::new("baseline_to", error.message))?;
+        if let (Some(from), Some(to)) = (baseline_from, baseline_to) {
+            if from > to {
+                return Err(ParameterError::new(
+                    "baseline_to",
+                    "the baseline period must not end before it starts",
+                ));
+            }
+        }
+
+        Ok(DelayCauseParameters {
+            group_by: group_by.transpose()?.unwrap_or_default(),
+            baseline_from,
+            baseline_to,
+        })
+    }
+
+    /// Whether the composition is compared to a baseline period.
+    fn has_baseline(&self) -> bool {
+        self.baseline_from.is_some() || self.baseline_to.is_some()
+    }
+
+    /// Format the parameters as the query parameters of a URI.
+    pub fn to_query(&self) -> String {
+        let mut parameters = vec![format!("group_by={}", self.group_by)];
+        if let Some(baseline_from) = self.baseline_from {
+            parameters.push(format!("baseline_from={}", baseline_from));
+        }
+        if let Some(baseline_to) = self.baseline_to {
+            parameters.push(format!("baseline_to={}", baseline_to));
+        }
+        parameters.join("&")
+    }
+}
+
+/// The totals of a carrier or airport: its code, its name, and its minutes
+/// delayed and number of delays by cause.
+type Totals = (String, String, [i64; 5], [i64; 5]);
+
+/// The ratio of `part` to `total`, if the total is not zero.
+fn ratio(part: i64, total: i64) -> Option<f64> {
+    if total != 0 {
+        Some(part as f64 / total as f64)
+    } else {
+        None
+    }
+}
+
+/// The difference of 2 ratios, if both are known.
+fn change(current: Option<f64>, baseline: Option<f64>) -> Option<f64> {
+    match (current, baseline) {
+        (Some(current), Some(baseline)) => Some(current - baseline),
+        _ => None,
+    }
+}
+
+/// The share of the minutes delayed which the carrier causes itself.
+fn self_inflicted_share(minutes: &[i64; 5]) -> Option<f64> {
+    let part = CAUSES
+        .iter()
+        .zip(minutes)
+        .filter(|((_, self_inflicted), _)| *self_inflicted)
+        .map(|(_, minutes)| minutes)
+        .sum();
+    ratio(part, minutes.iter().sum())
+}
+
+/// Load the totals of each carrier or airport of the `group` in the statistics
+/// matching the `filter`, summed by the database.
+fn load_totals(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    group: GroupBy,
+) -> Result<HashMap<i64, Totals>, Error> {
+    let query = match filtered_join(conn, filter)? {
+        Some(query) => query,
+        None => return Ok(HashMap::new()),
+    };
+
+    let (id, code, name) = group.identity();
+    let rows = query
+        .select((
+            id,
+            code,
+            name,
+            (
+                sum_int8("minutes_delayed_carrier"),
+                sum_int8("minutes_delayed_late_aircraft"),
+                sum_int8("minutes_delayed_national_aviation_system"),
+                sum_int8("minutes_delayed_security"),
+                sum_int8("minutes_delayed_weather"),
+            ),
+            (
+                sum_int8("number_of_delays_carrier"),
+                sum_int8("number_of_delays_late_aircraft"),
+                sum_int8("number_of_delays_national_aviation_system"),
+                sum_int8("number_of_delays_security"),
+                sum_int8("number_of_delays_weather"),
+            ),
+        ))
+        .group_by(group.grouping())
+        .load::<(
+            i64,
+            String,
+            String,
+            (i64, i64, i64, i64, i64),
+            (i64, i64, i64, i64, i64),
+        )>(conn)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, code, name, minutes, delays)| {
+            (
+                id,
+                (
+                    code,
+                    name,
+                    [minutes.0, minutes.1, minutes.2, minutes.3, minutes.4],
+                    [delays.0, delays.1, delays.2, delays.3, delays.4],
+                ),
+            )
+        })
+        .collect())
+}
+
+/// The composition of the delays of each carrier or airport in the statistics
+/// matching the `filter`, grouped as given by the `parameters`. If there is a
+/// baseline period, the composition is compared to that of the statistics
+/// matching the filter with its period replaced by the baseline period. The
+/// groups are ordered by code.
+pub fn load_delay_causes(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    parameters: &DelayCauseParameters,
+) -> Result<Vec<DelayCauses>, Error> {
+    let current = load_totals(conn, filter, parameters.group_by)?;
+    let baseline = if parameters.has_baseline() {
+        let filter = StatisticsFilter {
+            carrier: filter.carrier.clone(),
+            airport: filter.airport.clone(),
+            year: None,
+            month: filter.month,
+            from: parameters.baseline_from,
+            to: parameters.baseline_to,
+        };
+        Some(load_totals(conn, &filter, parameters.group_by)?)
+    } else {
+        None
+    };
+
+    let mut groups: Vec<DelayCauses> = current
+        .into_iter()
+        .map(|(id, (code, name, minutes, delays))| {
+            let baseline = baseline
+                .as_ref()
+                .and_then(|baseline| baseline.get(&id))
+                .map(|(_, _, minutes, delays)| (minutes, delays));
+            let minutes_total: i64 = minutes.iter().sum();
+            let delays_total: i64 = delays.iter().sum();
+            let baseline_totals = baseline
+                .map(|(minutes, delays)| (minutes.iter().sum::<i64>(), delays.iter().sum::<i64>()));
+
+            let causes = CAUSES
+                .iter()
+                .enumerate()
+                .map(|(index, (cause, _))| {
+                    let minutes_share = ratio(minutes[index], minutes_total);
+                    let delays_share = ratio(delays[index], delays_total);
+                    let (baseline_minutes_share, baseline_delays_share) =
+                        match (baseline, baseline_totals) {
+                            (Some((minutes, delays)), Some((minutes_total, delays_total))) => (
+                                ratio(minutes[index], minutes_total),
+                                ratio(delays[index], delays_total),
+                            ),
+                            _ => (None, None),
+                        };
+                    DelayCause {
+                        cause: String::from(*cause),
+                        minutes_delayed: minutes[index],
+                        number_of_delays: delays[index],
+                        minutes_share,
+                        delays_share,
+                        average_minutes_per_delay: ratio(minutes[index], delays[index]),
+                        minutes_share_change: change(minutes_share, baseline_minutes_share),
+                        delays_share_change: change(delays_share, baseline_delays_share),
+                    }
+                })
+                .collect();
+
+            let self_inflicted = self_inflicted_share(&minutes);
+            DelayCauses {
+                id,
+                code,
+                name,
+                minutes_delayed_total: minutes_total,
+                number_of_delays_total: delays_total,
+                self_inflicted_share: self_inflicted,
+                systemic_share: self_inflicted.map(|share| 1.0 - share),
+                self_inflicted_share_change: change(
+                    self_inflicted,
+                    baseline.and_then(|(minutes, _)| self_inflicted_share(minutes)),
+                ),
+                causes,
+            }
+        })
+        .collect();
+
+    groups.sort_by(|a, b| a.code.cmp(&b.code).then_with(|| a.id.cmp(&b.id)));
+    Ok(groups)
+}
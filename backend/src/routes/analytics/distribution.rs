@@ -0,0 +1,304 @@
+//! A module providing the distribution of a column over the monthly statistics
+//! of each carrier or airport: its percentiles, interquartile range and
+//! histogram.
+
+use super::series::mean;
+use super::views::{Bucket, Distribution, Percentile};
+
+use crate::routes::parameters::ParameterError;
+use crate::routes::statistics::filter::{filtered_join, GroupBy, StatisticsFilter};
+use crate::routes::statistics::timeseries::SeriesMetric;
+use diesel::{
+    dsl::sql,
+    prelude::*,
+    result::Error,
+    sql_types::{BigInt, Nullable, Text},
+};
+use rocket::{http::RawStr, request::FromFormValue};
+use std::fmt;
+
+/// The number of buckets of the histograms if none is given.
+const BINS: usize = 10;
+
+/// The most buckets a histogram may have.
+const MAX_BINS: usize = 100;
+
+/// The percentiles computed as given by the `percentiles` parameter, a comma
+/// separated list of numbers between 0 and 100, e.g. `percentiles=50,90,99`.
+#[derive(Clone, Debug)]
+pub struct Percentiles(Vec<f64>);
+
+impl<'v> FromFormValue<'v> for Percentiles {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let value = form_value
+            .url_decode()
+            .map_err(|_| ParameterError::new("percentiles", "not valid UTF-8"))?;
+
+        let mut percentiles = Vec::new();
+        for name in value.split(',').map(str::trim) {
+            match name.parse::<f64>() {
+                Ok(percentile) if percentile >= 0.0 && percentile <= 100.0 => {
+                    percentiles.push(percentile)
+                }
+                _ => {
+                    return Err(ParameterError::new(
+                        "percentiles",
+                        format!("`{}` is not a number between 0 and 100", name),
+                    ))
+                }
+            }
+        }
+
+        Ok(Percentiles(percentiles))
+    }
+}
+
+impl Default for Percentiles {
+    /// The median, the 90th and the 99th percentiles.
+    fn default() -> Self {
+        Percentiles(vec![50.0, 90.0, 99.0])
+    }
+}
+
+impl fmt::Display for Percentiles {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<String> = self.0.iter().map(f64::to_string).collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+/// The parameters of the distributions, taken from the query parameters of a
+/// request.
+pub struct DistributionParameters {
+    /// The column distributed.
+    pub metric: SeriesMetric,
+
+    /// What the statistics are grouped by, all of them forming a single group
+    /// if `None`.
+    pub by: Option<GroupBy>,
+
+    /// The percentiles computed.
+    pub percentiles: Percentiles,
+
+    /// The number of buckets of the histograms.
+    pub bins: usize,
+}
+
+impl DistributionParameters {
+    /// Check the `metric`, `by`, `percentiles` and `bins` parameters of a
+    /// request, the histograms have between 1 and 100 buckets.
+    pub fn check(
+        metric: Option<Result<SeriesMetric, ParameterError>>,
+        by: Option<Result<GroupBy, ParameterError>>,
+        percentiles: Option<Result<Percentiles, ParameterError>>,
+        bins: Option<Result<usize, &RawStr>>,
+    ) -> Result<Self, ParameterError> {
+        let bins = match bins {
+            None => BINS,
+            Some(Ok(bins)) if bins >= 1 && bins <= MAX_BINS => bins,
+            Some(_) => {
+                return Err(ParameterError::new(
+                    "bins",
+                    format!("the number of bins must be between 1 and {}", MAX_BINS),
+                ))
+            }
+        };
+
+        Ok(DistributionParameters {
+            metric: metric.transpose()?.unwrap_or_default(),
+            by: by
+                .transpose()
+                .map_err(|error| ParameterError::new("by", error.message))?,
+            percentiles: percentiles.transpose()?.unwrap_or_default(),
+            bins,
+        })
+    }
+
+    /// Format the parameters as the query parameters of a URI.
+    pub fn to_query(&self) -> String {
+        let mut parameters = vec![format!("metric={}", self.metric)];
+        if let Some(by) = self.by {
+            parameters.push(format!("by={}", by));
+        }
+        parameters.push(format!("percentiles={}", self.percentiles));
+        parameters.push(format!("bins={}", self.bins));
+        parameters.join("&")
+    }
+}
+
+/// The `percentile` of the `sorted` values, interpolating linearly between the
+/// closest ranks.
+fn percentile(sorted: &[f64], percentile: f64) -> f64 {
+    let position = percentile / 100.0 * (sorted.len() - 1) as f64;
+    let (lower, upper) = (position.floor() as usize, position.ceil() as usize);
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (position - lower as f64)
+}
+
+/// The histogram of the `sorted` values in `bins` buckets of equal width from
+/// the lowest to the highest value, the last bucket including its upper bound.
+/// If every value is the same there is a single bucket.
+fn histogram(sorted: &[f64], bins: usize) -> Vec<Bucket> {
+    let (min, max) = (sorted[0], sorted[sorted.len() - 1]);
+    if min == max {
+        return vec![Bucket {
+            lower: min,
+            upper: max,
+            count: sorted.len(),
+        }];
+    }
+
+    let width = (max - min) / bins as f64;
+    let mut counts = vec![0; bins];
+    for value in sorted {
+        let index = (((value - min) / width) as usize).min(bins - 1);
+        counts[index] += 1;
+    }
+    counts
+        .into_iter()
+        .enumerate()
+        .map(|(index, count)| Bucket {
+            lower: min + index as f64 * width,
+            upper: if index + 1 == bins {
+                max
+            } else {
+                min + (index + 1) as f64 * width
+            },
+            count,
+        })
+        .collect()
+}
+
+/// The distribution of the `values` of a group.
+fn distribute(
+    id: Option<i64>,
+    code: Option<String>,
+    mut values: Vec<f64>,
+    parameters: &DistributionParameters,
+) -> Distribution {
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    let (q1, q3) = (percentile(&values, 25.0), percentile(&values, 75.0));
+
+    Distribution {
+        id,
+        code,
+        count: values.len(),
+        min: values[0],
+        max: values[values.len() - 1],
+        mean: mean(&values).unwrap_or_default(),
+        percentiles: parameters
+            .percentiles
+            .0
+            .iter()
+            .map(|rank| Percentile {
+                percentile: *rank,
+                value: percentile(&values, *rank),
+            })
+            .collect(),
+        q1,
+        q3,
+        iqr: q3 - q1,
+        histogram: histogram(&values, parameters.bins),
+    }
+}
+
+/// The distributions of the metric over the statistics matching the `filter`,
+/// each set of statistics of a carrier at an airport in a month being a value.
+/// The statistics are grouped by carrier or airport, ordered by code, or else
+/// form a single group. Groups without statistics are left out.
+pub fn load_distributions(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    parameters: &DistributionParameters,
+) -> Result<Vec<Distribution>, Error> {
+    let query = match filtered_join(conn, filter)? {
+        Some(query) => query,
+        None => return Ok(Vec::new()),
+    };
+
+    let key = |column: &str| match parameters.by {
+        Some(by) => format!("{}.{}", by.table(), column),
+        None => String::from("NULL"),
+    };
+    let mut rows = query.select((
+        sql::<Nullable<BigInt>>(&key("id")),
+        sql::<Nullable<Text>>(&key("code")),
+        sql::<BigInt>(&format!("statistics.{}", parameters.metric)),
+    ));
+    if parameters.by.is_some() {
+        rows = rows.order(sql::<BigInt>(&format!("{}, {}", key("code"), key("id"))));
+    }
+    let rows = rows.load::<(Option<i64>, Option<String>, i64)>(conn)?;
+
+    let mut groups: Vec<(Option<i64>, Option<String>, Vec<f64>)> = Vec::new();
+    for (id, code, value) in rows {
+        match groups.last_mut() {
+            Some((last_id, _, values)) if *last_id == id => values.push(value as f64),
+            _ => groups.push((id, code, vec![value as f64])),
+        }
+    }
+
+    Ok(groups
+        .into_iter()
+        .map(|(id, code, values)| distribute(id, code, values, parameters))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_interpolates_between_ranks() {
+        let sorted = [10.0, 20.0, 30.0, 40.0];
+
+        assert!((percentile(&sorted, 0.0) - 10.0).abs() < 1e-9);
+        assert!((percentile(&sorted, 50.0) - 25.0).abs() < 1e-9);
+        assert!((percentile(&sorted, 90.0) - 37.0).abs() < 1e-9);
+        assert!((percentile(&sorted, 100.0) - 40.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn percentile_of_a_single_value_is_the_value() {
+        assert!((percentile(&[7.0], 99.0) - 7.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn histogram_includes_the_highest_value_in_the_last_bucket() {
+        let sorted: Vec<f64> = (0..=10).map(f64::from).collect();
+
+        let buckets = histogram(&sorted, 5);
+
+        let counts: Vec<usize> = buckets.iter().map(|bucket| bucket.count).collect();
+        assert_eq!(counts, vec![2, 2, 2, 2, 3]);
+        let bounds: Vec<(f64, f64)> = buckets
+            .iter()
+            .map(|bucket| (bucket.lower, bucket.upper))
+            .collect();
+        assert_eq!(
+            bounds,
+            vec![(0.0, 2.0), (2.0, 4.0), (4.0, 6.0), (6.0, 8.0), (8.0, 10.0)]
+        );
+    }
+
+    #[test]
+    fn histogram_of_equal_values_is_a_single_bucket() {
+        let buckets = histogram(&[3.0, 3.0, 3.0], 10);
+
+        assert_eq!(buckets.len(), 1);
+        assert_eq!((buckets[0].lower, buckets[0].upper), (3.0, 3.0));
+        assert_eq!(buckets[0].count, 3);
+    }
+
+    #[test]
+    fn bins_must_be_a_number_between_1_and_100() {
+        for bins in &[Ok(0), Ok(MAX_BINS + 1), Err(RawStr::from_str("ten"))] {
+            let error = DistributionParameters::check(None, None, None, Some(*bins))
+                .err()
+                .unwrap();
+
+            assert_eq!(error.parameter, "bins");
+        }
+    }
+}
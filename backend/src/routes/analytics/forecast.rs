@@ -0,0 +1,394 @@
+//! A module providing the forecasting of the following months of a monthly
+//! series, by a seasonal naive model and a Holt-Winters model.
+
+use super::decomposition::PERIOD;
+use super::series::{mean, Series};
+use super::views::{Forecast, ForecastPoint, ModelForecast};
+
+use crate::routes::parameters::{ParameterError, YearMonth};
+use crate::routes::statistics::timeseries::SeriesMetric;
+use rocket::http::RawStr;
+
+/// The number of months forecast if none is given.
+const HORIZON: usize = 6;
+
+/// The most months which may be forecast.
+const MAX_HORIZON: usize = 36;
+
+/// The quantile of the standard normal distribution bounding the 95%
+/// prediction intervals.
+const Z_95: f64 = 1.96;
+
+/// The smoothing parameters tried when fitting the Holt-Winters model.
+const SMOOTHING: &[f64] = &[0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9];
+
+/// The parameters of a forecast, taken from the query parameters of a request.
+pub struct ForecastParameters {
+    /// The column forecast.
+    pub metric: SeriesMetric,
+
+    /// The number of months forecast.
+    pub horizon: usize,
+}
+
+impl ForecastParameters {
+    /// Check the `metric` and `horizon` parameters of a request, the horizon
+    /// is between 1 and 36 months.
+    pub fn check(
+        metric: Option<Result<SeriesMetric, ParameterError>>,
+        horizon: Option<Result<usize, &RawStr>>,
+    ) -> Result<Self, ParameterError> {
+        let horizon = match horizon {
+            None => HORIZON,
+            Some(Ok(horizon)) if horizon >= 1 && horizon <= MAX_HORIZON => horizon,
+            Some(_) => {
+                return Err(ParameterError::new(
+                    "horizon",
+                    format!("the horizon must be between 1 and {} months", MAX_HORIZON),
+                ))
+            }
+        };
+
+        Ok(ForecastParameters {
+            metric: metric.transpose()?.unwrap_or_default(),
+            horizon,
+        })
+    }
+
+    /// Format the parameters as the query parameters of a URI.
+    pub fn to_query(&self) -> String {
+        format!("metric={}&horizon={}", self.metric, self.horizon)
+    }
+}
+
+/// A fitted model: the point forecasts of the following months and the
+/// standard deviation of its one step errors.
+struct Fit {
+    forecasts: Vec<f64>,
+    sigma: f64,
+}
+
+/// The values of the `series`, the missing months being interpolated linearly
+/// between the months around them.
+fn interpolate(series: &Series) -> Vec<f64> {
+    let known: Vec<(usize, f64)> = series
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (_, value))| value.map(|value| (index, value)))
+        .collect();
+
+    known
+        .windows(2)
+        .flat_map(|pair| {
+            let ((start, from), (end, to)) = (pair[0], pair[1]);
+            (start..end).map(move |index| {
+                from + (to - from) * (index - start) as f64 / (end - start) as f64
+            })
+        })
+        .chain(known.last().map(|(_, value)| *value))
+        .collect()
+}
+
+/// The root mean square of the `errors`.
+fn root_mean_square(errors: &[f64]) -> f64 {
+    mean(
+        &errors
+            .iter()
+            .map(|error| error * error)
+            .collect::<Vec<f64>>(),
+    )
+    .unwrap_or(0.0)
+    .sqrt()
+}
+
+/// Forecast each month as the same month of the last year, which needs a year
+/// of values.
+fn seasonal_naive(values: &[f64], horizon: usize) -> Option<Fit> {
+    if values.len() < PERIOD {
+        return None;
+    }
+
+    let forecasts = (0..horizon)
+        .map(|step| values[values.len() - PERIOD + step % PERIOD])
+        .collect();
+    let errors: Vec<f64> = (PERIOD..values.len())
+        .map(|index| values[index] - values[index - PERIOD])
+        .collect();
+
+    Some(Fit {
+        forecasts,
+        sigma: root_mean_square(&errors),
+    })
+}
+
+/// Run the additive Holt-Winters model with the smoothing parameters `alpha`,
+/// `beta` and `gamma` over the `values`. The level, trend and seasonal
+/// components are initialised from the first 2 years. Return the one step
+/// errors along with the final level, trend and seasonal components.
+fn holt_winters_run(
+    values: &[f64],
+    (alpha, beta, gamma): (f64, f64, f64),
+) -> (Vec<f64>, f64, f64, Vec<f64>) {
+    let first = mean(&values[..PERIOD]).unwrap_or(0.0);
+    let second = mean(&values[PERIOD..2 * PERIOD]).unwrap_or(0.0);
+    let mut level = first;
+    let mut trend = (second - first) / PERIOD as f64;
+    let mut seasonal: Vec<f64> = values[..PERIOD].iter().map(|value| value - first).collect();
+
+    let mut errors = Vec::new();
+    for (index, value) in values.iter().enumerate().skip(PERIOD) {
+        let season = index % PERIOD;
+        let forecast = level + trend + seasonal[season];
+        errors.push(value - forecast);
+
+        let previous_level = level;
+        level = alpha * (value - seasonal[season]) + (1.0 - alpha) * (level + trend);
+        trend = beta * (level - previous_level) + (1.0 - beta) * trend;
+        seasonal[season] = gamma * (value - level) + (1.0 - gamma) * seasonal[season];
+    }
+
+    (errors, level, trend, seasonal)
+}
+
+/// Forecast by the additive Holt-Winters model, the smoothing parameters being
+/// those minimising the squared one step errors. It needs 2 years of values.
+fn holt_winters(values: &[f64], horizon: usize) -> Option<Fit> {
+    if values.len() < 2 * PERIOD {
+        return None;
+    }
+
+    let mut best: Option<(f64, (f64, f64, f64))> = None;
+    for &alpha in SMOOTHING {
+        for &beta in SMOOTHING {
+            for &gamma in SMOOTHING {
+                let (errors, _, _, _) = holt_winters_run(values, (alpha, beta, gamma));
+                let sse: f64 = errors.iter().map(|error| error * error).sum();
+                if best.map_or(true, |(best_sse, _)| sse < best_sse) {
+                    best = Some((sse, (alpha, beta, gamma)));
+                }
+            }
+        }
+    }
+
+    let (_, smoothing) = best?;
+    let (errors, level, trend, seasonal) = holt_winters_run(values, smoothing);
+    let forecasts = (0..horizon)
+        .map(|step| level + (step + 1) as f64 * trend + seasonal[(values.len() + step) % PERIOD])
+        .collect();
+
+    Some(Fit {
+        forecasts,
+        sigma: root_mean_square(&errors),
+    })
+}
+
+/// The mean absolute percentage error of a `model` forecasting the last
+/// `horizon` values from those before them. Values of zero are left out, as
+/// their percentage error is not defined. `None` if there are too few values
+/// to fit the model without the last ones.
+fn backtest(
+    model: fn(&[f64], usize) -> Option<Fit>,
+    values: &[f64],
+    horizon: usize,
+) -> Option<f64> {
+    if values.len() <= horizon {
+        return None;
+    }
+
+    let (training, actual) = values.split_at(values.len() - horizon);
+    let fit = model(training, horizon)?;
+    let percentages: Vec<f64> = actual
+        .iter()
+        .zip(fit.forecasts)
+        .filter(|(actual, _)| **actual != 0.0)
+        .map(|(actual, forecast)| ((actual - forecast) / actual).abs() * 100.0)
+        .collect();
+    mean(&percentages)
+}
+
+/// Forecast the months following the monthly `series` by each model, with 95%
+/// prediction intervals widening with the square root of the steps ahead, and
+/// the backtest error of each model. A model without enough values has
+/// neither forecasts nor a backtest error.
+pub fn forecast(series: &Series, parameters: &ForecastParameters) -> Forecast {
+    let values = interpolate(series);
+    let horizon = parameters.horizon;
+
+    let mut months: Vec<YearMonth> = Vec::new();
+    let mut month = series.last().map(|(month, _)| *month);
+    for _ in 0..horizon {
+        month = month.and_then(|month| month.next());
+        match month {
+            Some(month) => months.push(month),
+            None => break,
+        }
+    }
+
+    let models: &[(&str, fn(&[f64], usize) -> Option<Fit>)] = &[
+        ("seasonal_naive", seasonal_naive),
+        ("holt_winters", holt_winters),
+    ];
+    let models = models
+        .iter()
+        .map(|(name, model)| {
+            let points = match model(&values, horizon) {
+                Some(Fit { forecasts, sigma }) => months
+                    .iter()
+                    .zip(forecasts)
+                    .enumerate()
+                    .map(|(step, (month, forecast))| {
+                        let margin = Z_95 * sigma * ((step + 1) as f64).sqrt();
+                        ForecastPoint {
+                            month: *month,
+                            forecast,
+                            lower: forecast - margin,
+                            upper: forecast + margin,
+                        }
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+
+            ModelForecast {
+                model: String::from(*name),
+                mape: backtest(*model, &values, horizon),
+                points,
+            }
+        })
+        .collect();
+
+    Forecast {
+        metric: parameters.metric.to_string(),
+        horizon,
+        models,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A year of monthly values, repeated by the seasonal series.
+    const PATTERN: [f64; PERIOD] = [
+        10.0, 12.0, 15.0, 14.0, 18.0, 25.0, 30.0, 28.0, 20.0, 16.0, 11.0, 13.0,
+    ];
+
+    /// The `years` of a perfectly seasonal series.
+    fn seasonal(years: usize) -> Vec<f64> {
+        PATTERN
+            .iter()
+            .cloned()
+            .cycle()
+            .take(years * PERIOD)
+            .collect()
+    }
+
+    fn assert_close(actual: &[f64], expected: &[f64]) {
+        assert_eq!(actual.len(), expected.len());
+        for (actual, expected) in actual.iter().zip(expected) {
+            assert!(
+                (actual - expected).abs() < 1e-9,
+                "{:?} != {:?}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn interpolate_fills_missing_months_linearly() {
+        let series: Series = [Some(1.0), None, None, Some(4.0), Some(2.0)]
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                (
+                    YearMonth {
+                        year: 2010,
+                        month: index as u32 + 1,
+                    },
+                    *value,
+                )
+            })
+            .collect();
+
+        assert_close(&interpolate(&series), &[1.0, 2.0, 3.0, 4.0, 2.0]);
+    }
+
+    #[test]
+    fn seasonal_naive_forecasts_a_seasonal_series_as_itself() {
+        let fit = seasonal_naive(&seasonal(2), 18).unwrap();
+
+        assert_close(&fit.forecasts, &seasonal(2)[..18]);
+        assert_eq!(fit.sigma, 0.0);
+    }
+
+    #[test]
+    fn seasonal_naive_needs_a_year() {
+        assert!(seasonal_naive(&seasonal(1)[..PERIOD - 1], 6).is_none());
+    }
+
+    #[test]
+    fn holt_winters_run_of_a_constant_series_has_no_errors() {
+        let values = vec![7.0; 3 * PERIOD];
+        let (errors, level, trend, seasonal) = holt_winters_run(&values, (0.5, 0.5, 0.5));
+
+        assert_close(&errors, &[0.0; 2 * PERIOD]);
+        assert_close(&[level, trend], &[7.0, 0.0]);
+        assert_close(&seasonal, &[0.0; PERIOD]);
+    }
+
+    #[test]
+    fn holt_winters_forecasts_a_seasonal_series_as_itself() {
+        let fit = holt_winters(&seasonal(3), 12).unwrap();
+
+        assert_close(&fit.forecasts, &PATTERN);
+        assert!(fit.sigma.abs() < 1e-9);
+    }
+
+    #[test]
+    fn holt_winters_needs_2_years() {
+        assert!(holt_winters(&seasonal(2)[..2 * PERIOD - 1], 6).is_none());
+    }
+
+    #[test]
+    fn backtest_of_a_seasonal_series_has_no_error() {
+        let mape = backtest(seasonal_naive, &seasonal(3), 6).unwrap();
+
+        assert!(mape.abs() < 1e-9);
+    }
+
+    #[test]
+    fn backtest_leaves_out_values_of_zero() {
+        // Each month is forecast as 1, which is off by 50% in the first month
+        // and exact in the second. The third month, of zero, is left out.
+        let mut values = vec![1.0; PERIOD];
+        values.extend(&[2.0, 1.0, 0.0]);
+
+        let mape = backtest(seasonal_naive, &values, 3).unwrap();
+
+        assert!((mape - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn backtest_needs_more_values_than_the_horizon() {
+        assert!(backtest(seasonal_naive, &seasonal(1), PERIOD).is_none());
+    }
+
+    #[test]
+    fn horizon_defaults_to_6_months() {
+        let parameters = ForecastParameters::check(None, None).unwrap();
+
+        assert_eq!(parameters.horizon, HORIZON);
+    }
+
+    #[test]
+    fn horizon_must_be_a_number_between_1_and_36() {
+        for horizon in &[Ok(0), Ok(MAX_HORIZON + 1), Err(RawStr::from_str("abc"))] {
+            let error = ForecastParameters::check(None, Some(*horizon))
+                .err()
+                .unwrap();
+
+            assert_eq!(error.parameter, "horizon");
+        }
+    }
+}
@@ -0,0 +1,130 @@
+//! A module dealing with the `get_analytics_anomalies` routes.
+
+use super::anomalies::{detect_anomalies, AnomalyParameters, Method};
+use super::views::Anomaly;
+
+use crate::routes::parameters::{join_query, ParameterError};
+use crate::routes::statistics::filter::StatisticsFilterForm;
+use crate::routes::statistics::timeseries::SeriesMetric;
+use crate::CorgisDbConn;
+use diesel::result::Error;
+use rayon::prelude::*;
+use rocket::{get, http::RawStr, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+
+/// Get the JSON representation of the anomalous months of the statistics.
+#[get(
+    "/anomalies?<metric>&<method>&<threshold>&<filter..>",
+    format = "application/json",
+    rank = 1
+)]
+pub fn get_analytics_anomalies_json(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    method: Option<Result<Method, ParameterError>>,
+    threshold: Option<Result<f64, &RawStr>>,
+) -> Result<Result<Json<Vec<Anomaly>>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match AnomalyParameters::check(metric, method, threshold) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    detect_anomalies(&conn, &filter, &parameters).map(|data| Ok(Json(data)))
+}
+
+/// Get the CSV representation of the anomalous months of the statistics.
+#[get(
+    "/anomalies?<metric>&<method>&<threshold>&<filter..>",
+    format = "text/csv",
+    rank = 2
+)]
+pub fn get_analytics_anomalies_csv(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    method: Option<Result<Method, ParameterError>>,
+    threshold: Option<Result<f64, &RawStr>>,
+) -> Result<Result<Csv<Vec<Anomaly>>, ParameterError>, Error> {
+    fn convertor(anomalies: &Vec<Anomaly>) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        for anomaly in anomalies {
+            wtr.serialize(anomaly).unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match AnomalyParameters::check(metric, method, threshold) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    detect_anomalies(&conn, &filter, &parameters).map(|data| Ok(Csv(data, convertor)))
+}
+
+/// Get the HAL representation of the anomalous months of the statistics. Each
+/// anomaly links to its statistics, carrier and airport.
+#[get(
+    "/anomalies?<metric>&<method>&<threshold>&<filter..>",
+    format = "application/hal+json",
+    rank = 3
+)]
+pub fn get_analytics_anomalies_hal(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    method: Option<Result<Method, ParameterError>>,
+    threshold: Option<Result<f64, &RawStr>>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match AnomalyParameters::check(metric, method, threshold) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![filter.to_string(), parameters.to_query()]);
+    let anomalies = detect_anomalies(&conn, &filter, &parameters)?
+        .into_par_iter()
+        .map(|data| {
+            HalResource::new(&data)
+                .with_link("statistics", format!("/statistics/{}", data.id))
+                .with_link("carrier", format!("/carriers/{}", data.carrier_id))
+                .with_link("airport", format!("/airports/{}", data.airport_id))
+        })
+        .collect();
+
+    let result = HalResource::new(())
+        .with_link("self", format!("/analytics/anomalies?{}", query))
+        .with_resources("anomalies", anomalies);
+
+    Ok(Ok(Json(result)))
+}
+
+/// Get the default representation of the anomalous months of the statistics.
+/// This is executed if the other routes are not matched.
+#[get("/anomalies?<metric>&<method>&<threshold>&<filter..>", rank = 4)]
+pub fn get_analytics_anomalies_default(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    method: Option<Result<Method, ParameterError>>,
+    threshold: Option<Result<f64, &RawStr>>,
+) -> Result<Result<Json<Vec<Anomaly>>, ParameterError>, diesel::result::Error> {
+    get_analytics_anomalies_json(conn, filter, metric, method, threshold)
+}
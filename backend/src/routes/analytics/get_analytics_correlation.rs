@@ -0,0 +1,122 @@
+//! A module dealing with the `get_analytics_correlation` routes.
+
+use super::correlation::{correlate, CorrelationMetrics};
+use super::views::Correlation;
+
+use crate::routes::parameters::{join_query, ParameterError};
+use crate::routes::statistics::filter::StatisticsFilterForm;
+use crate::CorgisDbConn;
+use diesel::result::Error;
+use rocket::{get, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+
+/// Get the JSON representation of the correlation matrices of the `metrics`.
+#[get(
+    "/correlation?<metrics>&<filter..>",
+    format = "application/json",
+    rank = 1
+)]
+pub fn get_analytics_correlation_json(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metrics: Option<Result<CorrelationMetrics, ParameterError>>,
+) -> Result<Result<Json<Correlation>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let metrics = match CorrelationMetrics::check(metrics) {
+        Ok(metrics) => metrics,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    correlate(&conn, &filter, &metrics).map(|data| Ok(Json(data)))
+}
+
+/// Get the CSV representation of the correlation matrices of the `metrics`, a
+/// row for each metric of each method, with a column for each metric.
+#[get("/correlation?<metrics>&<filter..>", format = "text/csv", rank = 2)]
+pub fn get_analytics_correlation_csv(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metrics: Option<Result<CorrelationMetrics, ParameterError>>,
+) -> Result<Result<Csv<Correlation>, ParameterError>, Error> {
+    fn convertor(correlation: &Correlation) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        let mut header = vec![String::from("method"), String::from("metric")];
+        header.extend(correlation.metrics.iter().cloned());
+        wtr.write_record(&header).unwrap();
+
+        let methods = [
+            ("pearson", &correlation.pearson),
+            ("spearman", &correlation.spearman),
+        ];
+        for (method, matrix) in &methods {
+            for (metric, row) in correlation.metrics.iter().zip(matrix.iter()) {
+                let mut record = vec![method.to_string(), metric.clone()];
+                record.extend(
+                    row.iter()
+                        .map(|value| value.map_or_else(String::new, |value| value.to_string())),
+                );
+                wtr.write_record(&record).unwrap();
+            }
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let metrics = match CorrelationMetrics::check(metrics) {
+        Ok(metrics) => metrics,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    correlate(&conn, &filter, &metrics).map(|data| Ok(Csv(data, convertor)))
+}
+
+/// Get the HAL representation of the correlation matrices of the `metrics`.
+#[get(
+    "/correlation?<metrics>&<filter..>",
+    format = "application/hal+json",
+    rank = 3
+)]
+pub fn get_analytics_correlation_hal(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metrics: Option<Result<CorrelationMetrics, ParameterError>>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let metrics = match CorrelationMetrics::check(metrics) {
+        Ok(metrics) => metrics,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![filter.to_string(), metrics.to_query()]);
+    let data = correlate(&conn, &filter, &metrics)?;
+    let result = HalResource::new(&data)
+        .with_link("self", format!("/analytics/correlation?{}", query))
+        .with_link("statistics", format!("/statistics?{}", filter));
+
+    Ok(Ok(Json(result)))
+}
+
+/// Get the default representation of the correlation matrices of the
+/// `metrics`. This is executed if the other routes are not matched.
+#[get("/correlation?<metrics>&<filter..>", rank = 4)]
+pub fn get_analytics_correlation_default(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metrics: Option<Result<CorrelationMetrics, ParameterError>>,
+) -> Result<Result<Json<Correlation>, ParameterError>, diesel::result::Error> {
+    get_analytics_correlation_json(conn, filter, metrics)
+}
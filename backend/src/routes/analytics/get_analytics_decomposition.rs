@@ -0,0 +1,147 @@
+//! A module dealing with the `get_analytics_decomposition` routes.
+
+use super::decomposition::{decompose, Model};
+use super::series::load_series;
+use super::views::Decomposition;
+
+use crate::routes::parameters::{join_query, ParameterError};
+use crate::routes::statistics::filter::{StatisticsFilter, StatisticsFilterForm};
+use crate::routes::statistics::timeseries::SeriesMetric;
+use crate::CorgisDbConn;
+use diesel::result::Error;
+use rocket::{get, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+
+fn get_analytics_decomposition_data(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    metric: SeriesMetric,
+    model: Model,
+) -> Result<Decomposition, Error> {
+    let series = load_series(conn, filter, metric)?;
+    Ok(decompose(&series, metric.to_string(), model))
+}
+
+/// Check the `metric` and `model` parameters of a request.
+fn check_parameters(
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    model: Option<Result<Model, ParameterError>>,
+) -> Result<(SeriesMetric, Model), ParameterError> {
+    Ok((
+        metric.transpose()?.unwrap_or_default(),
+        model.transpose()?.unwrap_or_default(),
+    ))
+}
+
+/// Get the JSON representation of the seasonal decomposition of the monthly
+/// series of the `metric`.
+#[get(
+    "/decomposition?<metric>&<model>&<filter..>",
+    format = "application/json",
+    rank = 1
+)]
+pub fn get_analytics_decomposition_json(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    model: Option<Result<Model, ParameterError>>,
+) -> Result<Result<Json<Decomposition>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (metric, model) = match check_parameters(metric, model) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    get_analytics_decomposition_data(&conn, &filter, metric, model).map(|data| Ok(Json(data)))
+}
+
+/// Get the CSV representation of the seasonal decomposition of the monthly
+/// series of the `metric`, a row for each month.
+#[get(
+    "/decomposition?<metric>&<model>&<filter..>",
+    format = "text/csv",
+    rank = 2
+)]
+pub fn get_analytics_decomposition_csv(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    model: Option<Result<Model, ParameterError>>,
+) -> Result<Result<Csv<Decomposition>, ParameterError>, Error> {
+    fn convertor(decomposition: &Decomposition) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        for point in &decomposition.points {
+            wtr.serialize(point).unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (metric, model) = match check_parameters(metric, model) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    get_analytics_decomposition_data(&conn, &filter, metric, model)
+        .map(|data| Ok(Csv(data, convertor)))
+}
+
+/// Get the HAL representation of the seasonal decomposition of the monthly
+/// series of the `metric`.
+#[get(
+    "/decomposition?<metric>&<model>&<filter..>",
+    format = "application/hal+json",
+    rank = 3
+)]
+pub fn get_analytics_decomposition_hal(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    model: Option<Result<Model, ParameterError>>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (metric, model) = match check_parameters(metric, model) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![
+        filter.to_string(),
+        format!("metric={}", metric),
+        format!("model={}", model),
+    ]);
+    let series = join_query(vec![filter.to_string(), format!("metric={}", metric)]);
+    let data = get_analytics_decomposition_data(&conn, &filter, metric, model)?;
+    let result = HalResource::new(&data)
+        .with_link("self", format!("/analytics/decomposition?{}", query))
+        .with_link("timeseries", format!("/statistics/timeseries?{}", series));
+
+    Ok(Ok(Json(result)))
+}
+
+/// Get the default representation of the seasonal decomposition of the
+/// monthly series of the `metric`. This is executed if the other routes are
+/// not matched.
+#[get("/decomposition?<metric>&<model>&<filter..>", rank = 4)]
+pub fn get_analytics_decomposition_default(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    model: Option<Result<Model, ParameterError>>,
+) -> Result<Result<Json<Decomposition>, ParameterError>, diesel::result::Error> {
+    get_analytics_decomposition_json(conn, filter, metric, model)
+}
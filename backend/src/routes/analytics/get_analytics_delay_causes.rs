@@ -0,0 +1,163 @@
+//! A module dealing with the `get_analytics_delay_causes` routes.
+
+use super::delay_causes::{load_delay_causes, DelayCauseParameters};
+use super::views::DelayCauses;
+
+use crate::routes::parameters::{join_query, ParameterError, YearMonth};
+use crate::routes::statistics::filter::{GroupBy, StatisticsFilterForm};
+use crate::CorgisDbConn;
+use diesel::result::Error;
+use rayon::prelude::*;
+use rocket::{get, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+use serde::Serialize;
+
+/// Get the JSON representation of the composition of the delays by cause.
+#[get(
+    "/delay-causes?<group_by>&<baseline_from>&<baseline_to>&<filter..>",
+    format = "application/json",
+    rank = 1
+)]
+pub fn get_analytics_delay_causes_json(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    group_by: Option<Result<GroupBy, ParameterError>>,
+    baseline_from: Option<Result<YearMonth, ParameterError>>,
+    baseline_to: Option<Result<YearMonth, ParameterError>>,
+) -> Result<Result<Json<Vec<DelayCauses>>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match DelayCauseParameters::check(group_by, baseline_from, baseline_to) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    load_delay_causes(&conn, &filter, &parameters).map(|data| Ok(Json(data)))
+}
+
+/// Get the CSV representation of the composition of the delays by cause, a row
+/// for each cause of each carrier or airport.
+#[get(
+    "/delay-causes?<group_by>&<baseline_from>&<baseline_to>&<filter..>",
+    format = "text/csv",
+    rank = 2
+)]
+pub fn get_analytics_delay_causes_csv(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    group_by: Option<Result<GroupBy, ParameterError>>,
+    baseline_from: Option<Result<YearMonth, ParameterError>>,
+    baseline_to: Option<Result<YearMonth, ParameterError>>,
+) -> Result<Result<Csv<Vec<DelayCauses>>, ParameterError>, Error> {
+    fn convertor(groups: &Vec<DelayCauses>) -> String {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            id: i64,
+            code: &'a str,
+            name: &'a str,
+            cause: &'a str,
+            minutes_delayed: i64,
+            number_of_delays: i64,
+            minutes_share: Option<f64>,
+            delays_share: Option<f64>,
+            average_minutes_per_delay: Option<f64>,
+            minutes_share_change: Option<f64>,
+            delays_share_change: Option<f64>,
+        }
+
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        for group in groups {
+            for cause in &group.causes {
+                wtr.serialize(Record {
+                    id: group.id,
+                    code: &group.code,
+                    name: &group.name,
+                    cause: &cause.cause,
+                    minutes_delayed: cause.minutes_delayed,
+                    number_of_delays: cause.number_of_delays,
+                    minutes_share: cause.minutes_share,
+                    delays_share: cause.delays_share,
+                    average_minutes_per_delay: cause.average_minutes_per_delay,
+                    minutes_share_change: cause.minutes_share_change,
+                    delays_share_change: cause.delays_share_change,
+                })
+                .unwrap();
+            }
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match DelayCauseParameters::check(group_by, baseline_from, baseline_to) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    load_delay_causes(&conn, &filter, &parameters).map(|data| Ok(Csv(data, convertor)))
+}
+
+/// Get the HAL representation of the composition of the delays by cause. Each
+/// carrier or airport links to itself.
+#[get(
+    "/delay-causes?<group_by>&<baseline_from>&<baseline_to>&<filter..>",
+    format = "application/hal+json",
+    rank = 3
+)]
+pub fn get_analytics_delay_causes_hal(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    group_by: Option<Result<GroupBy, ParameterError>>,
+    baseline_from: Option<Result<YearMonth, ParameterError>>,
+    baseline_to: Option<Result<YearMonth, ParameterError>>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match DelayCauseParameters::check(group_by, baseline_from, baseline_to) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![filter.to_string(), parameters.to_query()]);
+    let group = parameters.group_by;
+    let groups = load_delay_causes(&conn, &filter, &parameters)?
+        .into_par_iter()
+        .map(|data| {
+            HalResource::new(&data)
+                .with_link(group.to_string(), format!("/{}/{}", group.table(), data.id))
+        })
+        .collect();
+
+    let result = HalResource::new(())
+        .with_link("self", format!("/analytics/delay-causes?{}", query))
+        .with_resources("delay_causes", groups);
+
+    Ok(Ok(Json(result)))
+}
+
+/// Get the default representation of the composition of the delays by cause.
+/// This is executed if the other routes are not matched.
+#[get(
+    "/delay-causes?<group_by>&<baseline_from>&<baseline_to>&<filter..>",
+    rank = 4
+)]
+pub fn get_analytics_delay_causes_default(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    group_by: Option<Result<GroupBy, ParameterError>>,
+    baseline_from: Option<Result<YearMonth, ParameterError>>,
+    baseline_to: Option<Result<YearMonth, ParameterError>>,
+) -> Result<Result<Json<Vec<DelayCauses>>, ParameterError>, diesel::result::Error> {
+    get_analytics_delay_causes_json(conn, filter, group_by, baseline_from, baseline_to)
+}
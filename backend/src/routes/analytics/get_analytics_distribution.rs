@@ -0,0 +1,181 @@
+//! A module dealing with the `get_analytics_distribution` routes.
+
+use super::distribution::{load_distributions, DistributionParameters, Percentiles};
+use super::views::Distribution;
+
+use crate::routes::parameters::{join_query, ParameterError};
+use crate::routes::statistics::filter::{GroupBy, StatisticsFilterForm};
+use crate::routes::statistics::timeseries::SeriesMetric;
+use crate::CorgisDbConn;
+use diesel::result::Error;
+use rayon::prelude::*;
+use rocket::{get, http::RawStr, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+
+/// Get the JSON representation of the distributions of the `metric`.
+#[get(
+    "/distribution?<metric>&<by>&<percentiles>&<bins>&<filter..>",
+    format = "application/json",
+    rank = 1
+)]
+pub fn get_analytics_distribution_json(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    by: Option<Result<GroupBy, ParameterError>>,
+    percentiles: Option<Result<Percentiles, ParameterError>>,
+    bins: Option<Result<usize, &RawStr>>,
+) -> Result<Result<Json<Vec<Distribution>>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match DistributionParameters::check(metric, by, percentiles, bins) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    load_distributions(&conn, &filter, &parameters).map(|data| Ok(Json(data)))
+}
+
+/// Get the CSV representation of the distributions of the `metric`, a row for
+/// each group with a column for each percentile. The histograms are left out.
+#[get(
+    "/distribution?<metric>&<by>&<percentiles>&<bins>&<filter..>",
+    format = "text/csv",
+    rank = 2
+)]
+pub fn get_analytics_distribution_csv(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    by: Option<Result<GroupBy, ParameterError>>,
+    percentiles: Option<Result<Percentiles, ParameterError>>,
+    bins: Option<Result<usize, &RawStr>>,
+) -> Result<Result<Csv<Vec<Distribution>>, ParameterError>, Error> {
+    fn convertor(distributions: &Vec<Distribution>) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        if let Some(first) = distributions.first() {
+            let mut header: Vec<String> = vec!["id", "code", "count", "min", "max", "mean"]
+                .into_iter()
+                .map(String::from)
+                .collect();
+            header.extend(
+                first
+                    .percentiles
+                    .iter()
+                    .map(|percentile| format!("p{}", percentile.percentile)),
+            );
+            header.extend(vec![
+                String::from("q1"),
+                String::from("q3"),
+                String::from("iqr"),
+            ]);
+            wtr.write_record(&header).unwrap();
+        }
+        for distribution in distributions {
+            let mut record = vec![
+                distribution
+                    .id
+                    .map_or_else(String::new, |id| id.to_string()),
+                distribution.code.clone().unwrap_or_default(),
+                distribution.count.to_string(),
+                distribution.min.to_string(),
+                distribution.max.to_string(),
+                distribution.mean.to_string(),
+            ];
+            record.extend(
+                distribution
+                    .percentiles
+                    .iter()
+                    .map(|percentile| percentile.value.to_string()),
+            );
+            record.extend(vec![
+                distribution.q1.to_string(),
+                distribution.q3.to_string(),
+                distribution.iqr.to_string(),
+            ]);
+            wtr.write_record(&record).unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match DistributionParameters::check(metric, by, percentiles, bins) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    load_distributions(&conn, &filter, &parameters).map(|data| Ok(Csv(data, convertor)))
+}
+
+/// Get the HAL representation of the distributions of the `metric`. Each
+/// distribution of a carrier or airport links to it.
+#[get(
+    "/distribution?<metric>&<by>&<percentiles>&<bins>&<filter..>",
+    format = "application/hal+json",
+    rank = 3
+)]
+pub fn get_analytics_distribution_hal(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    by: Option<Result<GroupBy, ParameterError>>,
+    percentiles: Option<Result<Percentiles, ParameterError>>,
+    bins: Option<Result<usize, &RawStr>>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match DistributionParameters::check(metric, by, percentiles, bins) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![filter.to_string(), parameters.to_query()]);
+    let by = parameters.by;
+    let distributions = load_distributions(&conn, &filter, &parameters)?
+        .into_par_iter()
+        .map(|data| {
+            let resource = HalResource::new(&data);
+            match (by, data.id) {
+                (Some(by), Some(id)) => {
+                    resource.with_link(by.to_string(), format!("/{}/{}", by.table(), id))
+                }
+                _ => resource,
+            }
+        })
+        .collect();
+
+    let result = HalResource::new(())
+        .with_link("self", format!("/analytics/distribution?{}", query))
+        .with_resources("distributions", distributions);
+
+    Ok(Ok(Json(result)))
+}
+
+/// Get the default representation of the distributions of the `metric`. This
+/// is executed if the other routes are not matched.
+#[get(
+    "/distribution?<metric>&<by>&<percentiles>&<bins>&<filter..>",
+    rank = 4
+)]
+pub fn get_analytics_distribution_default(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    by: Option<Result<GroupBy, ParameterError>>,
+    percentiles: Option<Result<Percentiles, ParameterError>>,
+    bins: Option<Result<usize, &RawStr>>,
+) -> Result<Result<Json<Vec<Distribution>>, ParameterError>, diesel::result::Error> {
+    get_analytics_distribution_json(conn, filter, metric, by, percentiles, bins)
+}
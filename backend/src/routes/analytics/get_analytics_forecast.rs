@@ -0,0 +1,155 @@
+//! A module dealing with the `get_analytics_forecast` routes.
+
+use super::forecast::{forecast, ForecastParameters};
+use super::series::load_series;
+use super::views::Forecast;
+
+use crate::routes::parameters::{join_query, ParameterError, YearMonth};
+use crate::routes::statistics::filter::{StatisticsFilter, StatisticsFilterForm};
+use crate::routes::statistics::timeseries::SeriesMetric;
+use crate::CorgisDbConn;
+use diesel::result::Error;
+use rocket::{get, http::RawStr, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+use serde::Serialize;
+
+fn get_analytics_forecast_data(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    parameters: &ForecastParameters,
+) -> Result<Forecast, Error> {
+    let series = load_series(conn, filter, parameters.metric)?;
+    Ok(forecast(&series, parameters))
+}
+
+/// Get the JSON representation of the forecast of the monthly series of the
+/// `metric`, `horizon` months ahead.
+#[get(
+    "/forecast?<metric>&<horizon>&<filter..>",
+    format = "application/json",
+    rank = 1
+)]
+pub fn get_analytics_forecast_json(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    horizon: Option<Result<usize, &RawStr>>,
+) -> Result<Result<Json<Forecast>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match ForecastParameters::check(metric, horizon) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    get_analytics_forecast_data(&conn, &filter, &parameters).map(|data| Ok(Json(data)))
+}
+
+/// Get the CSV representation of the forecast of the monthly series of the
+/// `metric`, `horizon` months ahead. Each row holds a month forecast by a
+/// model, along with the backtest error of the model.
+#[get(
+    "/forecast?<metric>&<horizon>&<filter..>",
+    format = "text/csv",
+    rank = 2
+)]
+pub fn get_analytics_forecast_csv(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    horizon: Option<Result<usize, &RawStr>>,
+) -> Result<Result<Csv<Forecast>, ParameterError>, Error> {
+    fn convertor(forecast: &Forecast) -> String {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            model: &'a str,
+            mape: Option<f64>,
+            month: YearMonth,
+            forecast: f64,
+            lower: f64,
+            upper: f64,
+        }
+
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        for model in &forecast.models {
+            for point in &model.points {
+                wtr.serialize(Record {
+                    model: &model.model,
+                    mape: model.mape,
+                    month: point.month,
+                    forecast: point.forecast,
+                    lower: point.lower,
+                    upper: point.upper,
+                })
+                .unwrap();
+            }
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match ForecastParameters::check(metric, horizon) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    get_analytics_forecast_data(&conn, &filter, &parameters).map(|data| Ok(Csv(data, convertor)))
+}
+
+/// Get the HAL representation of the forecast of the monthly series of the
+/// `metric`, `horizon` months ahead.
+#[get(
+    "/forecast?<metric>&<horizon>&<filter..>",
+    format = "application/hal+json",
+    rank = 3
+)]
+pub fn get_analytics_forecast_hal(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    horizon: Option<Result<usize, &RawStr>>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match ForecastParameters::check(metric, horizon) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![filter.to_string(), parameters.to_query()]);
+    let series = join_query(vec![
+        filter.to_string(),
+        format!("metric={}", parameters.metric),
+    ]);
+    let data = get_analytics_forecast_data(&conn, &filter, &parameters)?;
+    let result = HalResource::new(&data)
+        .with_link("self", format!("/analytics/forecast?{}", query))
+        .with_link("timeseries", format!("/statistics/timeseries?{}", series));
+
+    Ok(Ok(Json(result)))
+}
+
+/// Get the default representation of the forecast of the monthly series of
+/// the `metric`, `horizon` months ahead. This is executed if the other routes
+/// are not matched.
+#[get("/forecast?<metric>&<horizon>&<filter..>", rank = 4)]
+pub fn get_analytics_forecast_default(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    horizon: Option<Result<usize, &RawStr>>,
+) -> Result<Result<Json<Forecast>, ParameterError>, diesel::result::Error> {
+    get_analytics_forecast_json(conn, filter, metric, horizon)
+}
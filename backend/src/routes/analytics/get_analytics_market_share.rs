@@ -0,0 +1,169 @@
+//! A module dealing with the `get_analytics_market_share` routes.
+
+use super::market_share::{load_market_share, Period};
+use super::views::MarketShare;
+
+use crate::routes::parameters::{check_identifier, join_query, Identifier, ParameterError};
+use crate::routes::statistics::filter::{StatisticsFilter, StatisticsFilterForm};
+use crate::CorgisDbConn;
+use diesel::result::Error;
+use rocket::{get, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+use serde::Serialize;
+
+/// Check the `airport` and `period` parameters of a request, the airport must
+/// be given and be an id or a code.
+fn check_parameters(
+    airport: Option<Result<Identifier, ParameterError>>,
+    period: Option<Result<Period, ParameterError>>,
+) -> Result<(Identifier, Period), ParameterError> {
+    Ok((
+        check_identifier(airport, "airport")?,
+        period.transpose()?.unwrap_or_default(),
+    ))
+}
+
+/// Get the JSON representation of the share of the flights of each carrier at
+/// the `airport` in each period.
+#[get(
+    "/market-share?<airport>&<period>&<filter..>",
+    format = "application/json",
+    rank = 1
+)]
+pub fn get_analytics_market_share_json(
+    conn: CorgisDbConn,
+    airport: Option<Result<Identifier, ParameterError>>,
+    period: Option<Result<Period, ParameterError>>,
+    filter: LenientForm<StatisticsFilterForm>,
+) -> Result<Result<Option<Json<MarketShare>>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (airport, period) = match check_parameters(airport, period) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    Ok(Ok(
+        load_market_share(&conn, &filter, &airport, period)?.map(Json)
+    ))
+}
+
+/// Get the CSV representation of the share of the flights of each carrier at
+/// the `airport` in each period, a row for each carrier in each period.
+#[get(
+    "/market-share?<airport>&<period>&<filter..>",
+    format = "text/csv",
+    rank = 2
+)]
+pub fn get_analytics_market_share_csv(
+    conn: CorgisDbConn,
+    airport: Option<Result<Identifier, ParameterError>>,
+    period: Option<Result<Period, ParameterError>>,
+    filter: LenientForm<StatisticsFilterForm>,
+) -> Result<Result<Option<Csv<MarketShare>>, ParameterError>, Error> {
+    fn convertor(market_share: &MarketShare) -> String {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            period: &'a str,
+            carrier_id: i64,
+            carrier_code: &'a str,
+            flights_total: i64,
+            share: Option<f64>,
+            period_flights_total: i64,
+            hhi: Option<f64>,
+        }
+
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        for period in &market_share.periods {
+            for carrier in &period.carriers {
+                wtr.serialize(Record {
+                    period: &period.period,
+                    carrier_id: carrier.carrier_id,
+                    carrier_code: &carrier.carrier_code,
+                    flights_total: carrier.flights_total,
+                    share: carrier.share,
+                    period_flights_total: period.flights_total,
+                    hhi: period.hhi,
+                })
+                .unwrap();
+            }
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (airport, period) = match check_parameters(airport, period) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    Ok(Ok(
+        load_market_share(&conn, &filter, &airport, period)?.map(|data| Csv(data, convertor))
+    ))
+}
+
+/// Get the HAL representation of the share of the flights of each carrier at
+/// the `airport` in each period.
+#[get(
+    "/market-share?<airport>&<period>&<filter..>",
+    format = "application/hal+json",
+    rank = 3
+)]
+pub fn get_analytics_market_share_hal(
+    conn: CorgisDbConn,
+    airport: Option<Result<Identifier, ParameterError>>,
+    period: Option<Result<Period, ParameterError>>,
+    filter: LenientForm<StatisticsFilterForm>,
+) -> Result<Result<Option<Json<HalResource>>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (airport, period) = match check_parameters(airport, period) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let data = match load_market_share(&conn, &filter, &airport, period)? {
+        Some(data) => data,
+        None => return Ok(Ok(None)),
+    };
+    let statistics = StatisticsFilter {
+        carrier: None,
+        airport: Some(airport.clone()),
+        year: filter.year,
+        month: filter.month,
+        from: filter.from,
+        to: filter.to,
+    };
+    let query = join_query(vec![statistics.to_string(), format!("period={}", period)]);
+    let result = HalResource::new(&data)
+        .with_link("self", format!("/analytics/market-share?{}", query))
+        .with_link("airport", format!("/airports/{}", data.airport_id))
+        .with_link("statistics", format!("/statistics?{}", statistics));
+
+    Ok(Ok(Some(Json(result))))
+}
+
+/// Get the default representation of the share of the flights of each carrier
+/// at the `airport` in each period. This is executed if the other routes are
+/// not matched.
+#[get("/market-share?<airport>&<period>&<filter..>", rank = 4)]
+pub fn get_analytics_market_share_default(
+    conn: CorgisDbConn,
+    airport: Option<Result<Identifier, ParameterError>>,
+    period: Option<Result<Period, ParameterError>>,
+    filter: LenientForm<StatisticsFilterForm>,
+) -> Result<Result<Option<Json<MarketShare>>, ParameterError>, diesel::result::Error> {
+    get_analytics_market_share_json(conn, airport, period, filter)
+}
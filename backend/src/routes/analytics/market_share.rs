@@ -0,0 +1,185 @@
+//! A module providing the share of the flights of each carrier at an airport
+//! in each period, and the concentration of the carriers there.
+
+use super::views::{CarrierShare, MarketShare, MarketSharePeriod};
+
+use crate::database::schema;
+use crate::routes::parameters::{Identifier, ParameterError, YearMonth};
+use crate::routes::statistics::filter::{filtered_join, sum_int8, StatisticsFilter};
+use chrono::{Datelike, NaiveDate};
+use diesel::{
+    dsl::{any, sql},
+    prelude::*,
+    result::Error,
+    sql_types::{BigInt, Date, Text},
+};
+use rocket::{http::RawStr, request::FromFormValue};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// The periods the flights are summed over, as given by the `period`
+/// parameter, either `month` or `year`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Period {
+    /// Sum the flights of each month.
+    Month,
+
+    /// Sum the flights of each year.
+    Year,
+}
+
+impl Default for Period {
+    /// The months.
+    fn default() -> Self {
+        Period::Month
+    }
+}
+
+impl<'v> FromFormValue<'v> for Period {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "month" => Ok(Period::Month),
+            "year" => Ok(Period::Year),
+            _ => Err(ParameterError::new(
+                "period",
+                format!("unknown period `{}`, expected month or year", form_value),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Period::Month => write!(f, "month"),
+            Period::Year => write!(f, "year"),
+        }
+    }
+}
+
+impl Period {
+    /// The SQL expression of the first day of the period of a set of
+    /// statistics.
+    fn sql(self) -> &'static str {
+        match self {
+            Period::Month => "statistics.time",
+            Period::Year => "CAST(DATE_TRUNC('year', statistics.time) AS DATE)",
+        }
+    }
+
+    /// The label of the period starting on the `first_day`, formatted as
+    /// `YYYY-MM` or `YYYY`.
+    fn label(self, first_day: NaiveDate) -> String {
+        match self {
+            Period::Month => YearMonth::from(first_day).to_string(),
+            Period::Year => format!("{:04}", first_day.year()),
+        }
+    }
+}
+
+/// The Herfindahl-Hirschman index of the `shares`, the sum of the squares of
+/// the shares in percent, from near 0 for many small carriers to 10000 for a
+/// single carrier.
+fn herfindahl_hirschman(shares: &[f64]) -> f64 {
+    shares.iter().map(|share| (share * 100.0).powi(2)).sum()
+}
+
+/// The share of the flights of each carrier at the `airport` in each period of
+/// the statistics matching the `filter`, the airport of the filter being
+/// replaced and its carrier ignored. The periods are in chronological order
+/// and their carriers by decreasing share. `None` if the airport does not
+/// identify anything.
+pub fn load_market_share(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    airport: &Identifier,
+    period: Period,
+) -> Result<Option<MarketShare>, Error> {
+    let (airport_id, airport_code) = match schema::airports::table
+        .filter(schema::airports::id.eq(any(airport.airport_ids(conn)?)))
+        .select((schema::airports::id, schema::airports::code))
+        .first::<(i64, String)>(conn)
+        .optional()?
+    {
+        Some(airport) => airport,
+        None => return Ok(None),
+    };
+
+    let filter = StatisticsFilter {
+        carrier: None,
+        airport: Some(airport.clone()),
+        year: filter.year,
+        month: filter.month,
+        from: filter.from,
+        to: filter.to,
+    };
+    let rows = match filtered_join(conn, &filter)? {
+        Some(query) => query
+            .select((
+                sql::<Date>(period.sql()),
+                schema::carriers::id,
+                schema::carriers::code,
+                sum_int8("flights_total"),
+            ))
+            .group_by(sql::<BigInt>(&format!(
+                "{}, carriers.id, carriers.code",
+                period.sql()
+            )))
+            .order(sql::<Text>(&format!("{}, carriers.code", period.sql())))
+            .load::<(NaiveDate, i64, String, i64)>(conn)?,
+        None => Vec::new(),
+    };
+
+    let mut periods: Vec<(NaiveDate, Vec<(i64, String, i64)>)> = Vec::new();
+    for (first_day, carrier_id, carrier_code, flights_total) in rows {
+        match periods.last_mut() {
+            Some((last, carriers)) if *last == first_day => {
+                carriers.push((carrier_id, carrier_code, flights_total))
+            }
+            _ => periods.push((first_day, vec![(carrier_id, carrier_code, flights_total)])),
+        }
+    }
+
+    let periods = periods
+        .into_iter()
+        .map(|(first_day, carriers)| {
+            let flights_total: i64 = carriers.iter().map(|(_, _, flights)| flights).sum();
+            let mut carriers: Vec<CarrierShare> = carriers
+                .into_iter()
+                .map(|(carrier_id, carrier_code, flights)| CarrierShare {
+                    carrier_id,
+                    carrier_code,
+                    flights_total: flights,
+                    share: if flights_total > 0 {
+                        Some(flights as f64 / flights_total as f64)
+                    } else {
+                        None
+                    },
+                })
+                .collect();
+            carriers.sort_by(|a, b| {
+                b.share
+                    .partial_cmp(&a.share)
+                    .unwrap_or(Ordering::Equal)
+                    .then_with(|| a.carrier_code.cmp(&b.carrier_code))
+            });
+            let shares: Option<Vec<f64>> = carriers.iter().map(|carrier| carrier.share).collect();
+
+            MarketSharePeriod {
+                period: period.label(first_day),
+                flights_total,
+                hhi: shares.map(|shares| herfindahl_hirschman(&shares)),
+                carriers,
+            }
+        })
+        .collect();
+
+    Ok(Some(MarketShare {
+        airport_id,
+        airport_code,
+        period: period.to_string(),
+        periods,
+    }))
+}
@@ -0,0 +1,58 @@
+//! A module dealing with the "/analytics" routes, which analyse the statistics
+//! beyond what the database computes.
+
+mod anomalies;
+mod correlation;
+mod decomposition;
+mod delay_causes;
+mod distribution;
+mod forecast;
+mod get_analytics_anomalies;
+mod get_analytics_correlation;
+mod get_analytics_decomposition;
+mod get_analytics_delay_causes;
+mod get_analytics_distribution;
+mod get_analytics_forecast;
+mod get_analytics_market_share;
+mod market_share;
+mod series;
+mod views;
+
+use rocket::routes;
+
+/// Mount the analytics routes to the provided and `rocket` and return the resulting `rocket`.
+pub fn mount(rocket: rocket::Rocket) -> rocket::Rocket {
+    rocket.mount(
+        "/analytics",
+        routes!(
+            get_analytics_decomposition::get_analytics_decomposition_json,
+            get_analytics_decomposition::get_analytics_decomposition_csv,
+            get_analytics_decomposition::get_analytics_decomposition_hal,
+            get_analytics_decomposition::get_analytics_decomposition_default,
+            get_analytics_anomalies::get_analytics_anomalies_json,
+            get_analytics_anomalies::get_analytics_anomalies_csv,
+            get_analytics_anomalies::get_analytics_anomalies_hal,
+            get_analytics_anomalies::get_analytics_anomalies_default,
+            get_analytics_forecast::get_analytics_forecast_json,
+            get_analytics_forecast::get_analytics_forecast_csv,
+            get_analytics_forecast::get_analytics_forecast_hal,
+            get_analytics_forecast::get_analytics_forecast_default,
+            get_analytics_delay_causes::get_analytics_delay_causes_json,
+            get_analytics_delay_causes::get_analytics_delay_causes_csv,
+            get_analytics_delay_causes::get_analytics_delay_causes_hal,
+            get_analytics_delay_causes::get_analytics_delay_causes_default,
+            get_analytics_correlation::get_analytics_correlation_json,
+            get_analytics_correlation::get_analytics_correlation_csv,
+            get_analytics_correlation::get_analytics_correlation_hal,
+            get_analytics_correlation::get_analytics_correlation_default,
+            get_analytics_distribution::get_analytics_distribution_json,
+            get_analytics_distribution::get_analytics_distribution_csv,
+            get_analytics_distribution::get_analytics_distribution_hal,
+            get_analytics_distribution::get_analytics_distribution_default,
+            get_analytics_market_share::get_analytics_market_share_json,
+            get_analytics_market_share::get_analytics_market_share_csv,
+            get_analytics_market_share::get_analytics_market_share_hal,
+            get_analytics_market_share::get_analytics_market_share_default,
+        ),
+    )
+}
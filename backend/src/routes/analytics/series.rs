@@ -0,0 +1,64 @@
+//! A module providing the monthly series analysed by the analytics routes.
+
+use crate::routes::parameters::YearMonth;
+use crate::routes::statistics::filter::StatisticsFilter;
+use crate::routes::statistics::timeseries::{
+    load_timeseries, Fill, SeriesMetric, SeriesParameters,
+};
+use diesel::result::Error;
+
+/// A continuous monthly series, the value of a month without any statistics
+/// being `None`.
+pub type Series = Vec<(YearMonth, Option<f64>)>;
+
+/// Load the monthly series of the `metric` summed over the statistics matching
+/// the `filter`, from the first to the last month with statistics.
+pub fn load_series(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    metric: SeriesMetric,
+) -> Result<Series, Error> {
+    let parameters = SeriesParameters {
+        metric,
+        fill: Fill::Null,
+        rolling: None,
+        year_over_year: false,
+    };
+
+    Ok(load_timeseries(conn, filter.query(conn)?, &parameters)?
+        .points
+        .into_iter()
+        .map(|point| (point.month, point.value.map(|value| value as f64)))
+        .collect())
+}
+
+/// The mean of the `values`, `None` if there are none.
+pub fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        None
+    } else {
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+}
+
+/// The median of the `values`, `None` if there are none.
+pub fn median(values: &[f64]) -> Option<f64> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+    match sorted.len() {
+        0 => None,
+        length if length % 2 == 0 => Some((sorted[length / 2 - 1] + sorted[length / 2]) / 2.0),
+        length => Some(sorted[length / 2]),
+    }
+}
+
+/// The sample standard deviation of the `values`, `None` if there are fewer
+/// than 2.
+pub fn standard_deviation(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let mean = mean(values)?;
+    let sum_of_squares: f64 = values.iter().map(|value| (value - mean).powi(2)).sum();
+    Some((sum_of_squares / (values.len() - 1) as f64).sqrt())
+}
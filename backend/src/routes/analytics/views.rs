@@ -0,0 +1,333 @@
+//! A module containing the analytics structures returned by the API.
+
+use crate::routes::parameters::YearMonth;
+use serde::Serialize;
+
+/// The seasonal decomposition of a monthly series as returned by the API.
+#[derive(Serialize)]
+pub struct Decomposition {
+    /// The column summed for each month.
+    pub metric: String,
+
+    /// How the components combine, either `additive` or `multiplicative`.
+    pub model: String,
+
+    /// The change of the trend per year, by least squares, if the trend is
+    /// known for at least 2 months.
+    pub trend_slope_per_year: Option<f64>,
+
+    /// The seasonal component of each month of the year.
+    pub seasonal_indices: Vec<SeasonalIndex>,
+
+    /// The decomposition of each month of the series.
+    pub points: Vec<DecompositionPoint>,
+}
+
+/// The seasonal component of a month of the year.
+#[derive(Serialize)]
+pub struct SeasonalIndex {
+    /// The month of the year, starting at 1.
+    pub month: u32,
+
+    /// The seasonal component, if the month could be detrended.
+    pub value: Option<f64>,
+}
+
+/// The decomposition of a month of a series.
+#[derive(Serialize)]
+pub struct DecompositionPoint {
+    /// The month, formatted as `YYYY-MM`.
+    pub month: YearMonth,
+
+    /// The value of the month, null if there are no statistics for it.
+    pub value: Option<f64>,
+
+    /// The trend, null for the first and last 6 months or if a month around it
+    /// is missing.
+    pub trend: Option<f64>,
+
+    /// The seasonal component.
+    pub seasonal: Option<f64>,
+
+    /// What remains of the value without the trend and the seasonal component.
+    pub residual: Option<f64>,
+}
+
+/// A month in which the statistics of a carrier at an airport are unusual, as
+/// returned by the API.
+#[derive(Serialize)]
+pub struct Anomaly {
+    /// The id of the set of statistics of the month.
+    pub id: i64,
+
+    /// The id of the carrier.
+    pub carrier_id: i64,
+
+    /// The id of the airport.
+    pub airport_id: i64,
+
+    /// The month, formatted as `YYYY-MM`.
+    pub month: YearMonth,
+
+    /// The value of the metric in the month.
+    pub observed: i64,
+
+    /// The value expected from the history of the carrier at the airport.
+    pub expected: f64,
+
+    /// The lowest value which is not anomalous.
+    pub lower: f64,
+
+    /// The highest value which is not anomalous.
+    pub upper: f64,
+
+    /// The distance of the observed value from the expected value, relative to
+    /// the deviation of the history.
+    pub score: f64,
+}
+
+/// The forecast of the following months of a monthly series as returned by the
+/// API.
+#[derive(Serialize)]
+pub struct Forecast {
+    /// The column summed for each month.
+    pub metric: String,
+
+    /// The number of months forecast.
+    pub horizon: usize,
+
+    /// The forecast of each model.
+    pub models: Vec<ModelForecast>,
+}
+
+/// The forecast of a model.
+#[derive(Serialize)]
+pub struct ModelForecast {
+    /// The name of the model, either `seasonal_naive` or `holt_winters`.
+    pub model: String,
+
+    /// The mean absolute percentage error of the model forecasting the last
+    /// months of the series from the months before them, null if there are too
+    /// few months.
+    pub mape: Option<f64>,
+
+    /// The forecast months, empty if there are too few months to fit the
+    /// model.
+    pub points: Vec<ForecastPoint>,
+}
+
+/// A forecast month.
+#[derive(Serialize)]
+pub struct ForecastPoint {
+    /// The month, formatted as `YYYY-MM`.
+    pub month: YearMonth,
+
+    /// The forecast value.
+    pub forecast: f64,
+
+    /// The lower bound of the 95% prediction interval.
+    pub lower: f64,
+
+    /// The upper bound of the 95% prediction interval.
+    pub upper: f64,
+}
+
+/// The composition of the delays of a carrier or an airport by cause, as
+/// returned by the API.
+#[derive(Serialize)]
+pub struct DelayCauses {
+    /// The id of the carrier or airport.
+    pub id: i64,
+
+    /// The code of the carrier or airport.
+    pub code: String,
+
+    /// The name of the carrier or airport.
+    pub name: String,
+
+    /// The minutes delayed by all causes.
+    pub minutes_delayed_total: i64,
+
+    /// The number of delays by all causes.
+    pub number_of_delays_total: i64,
+
+    /// The share of the minutes delayed caused by the carrier itself, either
+    /// directly or by a late aircraft, if there are any minutes delayed.
+    pub self_inflicted_share: Option<f64>,
+
+    /// The share of the minutes delayed caused by the national aviation
+    /// system, security or the weather, if there are any minutes delayed.
+    pub systemic_share: Option<f64>,
+
+    /// The change of the self-inflicted share from the baseline period, null
+    /// without a baseline period.
+    pub self_inflicted_share_change: Option<f64>,
+
+    /// The delays of each cause.
+    pub causes: Vec<DelayCause>,
+}
+
+/// The delays of a cause.
+#[derive(Serialize)]
+pub struct DelayCause {
+    /// The cause, the suffix of its columns, e.g. `late_aircraft`.
+    pub cause: String,
+
+    /// The minutes delayed by the cause.
+    pub minutes_delayed: i64,
+
+    /// The number of delays by the cause.
+    pub number_of_delays: i64,
+
+    /// The share of the minutes delayed by the cause.
+    pub minutes_share: Option<f64>,
+
+    /// The share of the delays by the cause.
+    pub delays_share: Option<f64>,
+
+    /// The average minutes of a delay by the cause, if there are any.
+    pub average_minutes_per_delay: Option<f64>,
+
+    /// The change of the share of the minutes from the baseline period, null
+    /// without a baseline period.
+    pub minutes_share_change: Option<f64>,
+
+    /// The change of the share of the delays from the baseline period, null
+    /// without a baseline period.
+    pub delays_share_change: Option<f64>,
+}
+
+/// The correlations between columns of the statistics as returned by the API.
+#[derive(Serialize)]
+pub struct Correlation {
+    /// The columns correlated, in the order of the rows and columns of the
+    /// matrices.
+    pub metrics: Vec<String>,
+
+    /// The number of sets of statistics correlated over.
+    pub observations: usize,
+
+    /// The Pearson correlation of each pair of columns, null if either column
+    /// does not vary.
+    pub pearson: Vec<Vec<Option<f64>>>,
+
+    /// The Spearman rank correlation of each pair of columns, null if either
+    /// column does not vary.
+    pub spearman: Vec<Vec<Option<f64>>>,
+}
+
+/// The distribution of a column over the statistics of a carrier or an
+/// airport, or over all of them, as returned by the API.
+#[derive(Serialize)]
+pub struct Distribution {
+    /// The id of the carrier or airport, null if the statistics are not
+    /// grouped.
+    pub id: Option<i64>,
+
+    /// The code of the carrier or airport, null if the statistics are not
+    /// grouped.
+    pub code: Option<String>,
+
+    /// The number of sets of statistics.
+    pub count: usize,
+
+    /// The lowest value.
+    pub min: f64,
+
+    /// The highest value.
+    pub max: f64,
+
+    /// The mean of the values.
+    pub mean: f64,
+
+    /// The percentiles requested.
+    pub percentiles: Vec<Percentile>,
+
+    /// The first quartile, the 25th percentile.
+    pub q1: f64,
+
+    /// The third quartile, the 75th percentile.
+    pub q3: f64,
+
+    /// The interquartile range, between the first and third quartiles.
+    pub iqr: f64,
+
+    /// The number of values in each bucket of equal width from the lowest to
+    /// the highest value.
+    pub histogram: Vec<Bucket>,
+}
+
+/// A percentile of a distribution.
+#[derive(Serialize)]
+pub struct Percentile {
+    /// The rank of the percentile, between 0 and 100.
+    pub percentile: f64,
+
+    /// The value below which that percentage of the values fall, interpolated
+    /// between the closest ranks.
+    pub value: f64,
+}
+
+/// A bucket of a histogram.
+#[derive(Serialize)]
+pub struct Bucket {
+    /// The lower bound of the bucket, included.
+    pub lower: f64,
+
+    /// The upper bound of the bucket, excluded unless it is the last bucket.
+    pub upper: f64,
+
+    /// The number of values in the bucket.
+    pub count: usize,
+}
+
+/// The share of the flights of each carrier at an airport over time, as
+/// returned by the API.
+#[derive(Serialize)]
+pub struct MarketShare {
+    /// The id of the airport.
+    pub airport_id: i64,
+
+    /// The code of the airport.
+    pub airport_code: String,
+
+    /// The periods the flights are summed over, either `month` or `year`.
+    pub period: String,
+
+    /// The shares in each period, in chronological order.
+    pub periods: Vec<MarketSharePeriod>,
+}
+
+/// The shares of the carriers at an airport in a period.
+#[derive(Serialize)]
+pub struct MarketSharePeriod {
+    /// The period, formatted as `YYYY-MM` or `YYYY`.
+    pub period: String,
+
+    /// The total number of flights of every carrier.
+    pub flights_total: i64,
+
+    /// The Herfindahl-Hirschman index of the shares in percent, between 0 and
+    /// 10000, null if there are no flights.
+    pub hhi: Option<f64>,
+
+    /// The share of each carrier, by decreasing share.
+    pub carriers: Vec<CarrierShare>,
+}
+
+/// The share of the flights of a carrier at an airport in a period.
+#[derive(Serialize)]
+pub struct CarrierShare {
+    /// The id of the carrier.
+    pub carrier_id: i64,
+
+    /// The code of the carrier.
+    pub carrier_code: String,
+
+    /// The number of flights of the carrier.
+    pub flights_total: i64,
+
+    /// The ratio of the flights of the carrier to those of every carrier, null
+    /// if there are no flights.
+    pub share: Option<f64>,
+}
@@ -0,0 +1,281 @@
+//! A module providing the comparison of 2 carriers at the airports and in the
+//! months they share.
+
+use super::views::{CarrierComparison, CarrierPerformance, ComparisonRow};
+
+use crate::database::schema;
+use crate::routes::parameters::{Identifier, YearMonth};
+use crate::routes::statistics::filter::StatisticsFilter;
+use chrono::NaiveDate;
+use diesel::{dsl::any, prelude::*, result::Error};
+use std::collections::{BTreeMap, HashMap};
+
+/// The significance level of the test of the difference of the on-time rates.
+const SIGNIFICANCE: f64 = 0.05;
+
+/// The totals of a carrier at an airport in a month: the total flights, the
+/// flights on time and the minutes delayed due to the carrier, late aircraft,
+/// the national aviation system, security and the weather.
+#[derive(Clone, Copy, Default)]
+struct Totals {
+    flights_total: i64,
+    flights_on_time: i64,
+    minutes_delayed: [i64; 5],
+}
+
+impl std::ops::AddAssign for Totals {
+    fn add_assign(&mut self, other: Totals) {
+        self.flights_total += other.flights_total;
+        self.flights_on_time += other.flights_on_time;
+        for (minutes, other) in self.minutes_delayed.iter_mut().zip(&other.minutes_delayed) {
+            *minutes += other;
+        }
+    }
+}
+
+impl From<Totals> for CarrierPerformance {
+    fn from(totals: Totals) -> Self {
+        let minutes_delayed_total: i64 = totals.minutes_delayed.iter().sum();
+        let share = |index: usize| {
+            if minutes_delayed_total > 0 {
+                Some(totals.minutes_delayed[index] as f64 / minutes_delayed_total as f64)
+            } else {
+                None
+            }
+        };
+
+        CarrierPerformance {
+            flights_total: totals.flights_total,
+            flights_on_time: totals.flights_on_time,
+            on_time_rate: if totals.flights_total > 0 {
+                Some(totals.flights_on_time as f64 / totals.flights_total as f64)
+            } else {
+                None
+            },
+            minutes_delayed_total,
+            share_carrier: share(0),
+            share_late_aircraft: share(1),
+            share_national_aviation_system: share(2),
+            share_security: share(3),
+            share_weather: share(4),
+        }
+    }
+}
+
+/// The cumulative distribution function of the standard normal distribution,
+/// by the approximation of the error function of Abramowitz and Stegun
+/// (7.1.26), accurate to about 1e-7.
+fn normal_cdf(x: f64) -> f64 {
+    let z = x.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.327_591_1 * z);
+    let polynomial = t
+        * (0.254_829_592
+            + t * (-0.284_496_736
+                + t * (1.421_413_741 + t * (-1.453_152_027 + t * 1.061_405_429))));
+    let erf = 1.0 - polynomial * (-z * z).exp();
+    if x >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+/// Compare the totals of the 2 carriers in a row of the comparison. The
+/// difference of their on-time rates is tested by a two-proportion z-test,
+/// which is not possible if either carrier has no flights or if every flight,
+/// or none, is on time.
+fn compare(
+    airport_id: Option<i64>,
+    airport_code: Option<String>,
+    month: Option<YearMonth>,
+    a: Totals,
+    b: Totals,
+) -> ComparisonRow {
+    let a = CarrierPerformance::from(a);
+    let b = CarrierPerformance::from(b);
+
+    let difference = match (a.on_time_rate, b.on_time_rate) {
+        (Some(a), Some(b)) => Some(a - b),
+        _ => None,
+    };
+    let (n_a, n_b) = (a.flights_total as f64, b.flights_total as f64);
+    let pooled = (a.flights_on_time + b.flights_on_time) as f64 / (n_a + n_b);
+    let standard_error = (pooled * (1.0 - pooled) * (1.0 / n_a + 1.0 / n_b)).sqrt();
+    let z = difference
+        .filter(|_| standard_error > 0.0 && standard_error.is_finite())
+        .map(|difference| difference / standard_error);
+    let p_value = z.map(|z| 2.0 * (1.0 - normal_cdf(z.abs())));
+
+    ComparisonRow {
+        airport_id,
+        airport_code,
+        month,
+        a,
+        b,
+        on_time_rate_difference: difference,
+        z,
+        p_value,
+        significant: p_value.map(|p_value| p_value < SIGNIFICANCE),
+    }
+}
+
+/// Load the totals of the carriers identified by `carrier` in the statistics
+/// matching the `filter`, by airport and month. `None` if the carrier does not
+/// identify anything.
+fn load_totals(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    carrier: &Identifier,
+) -> Result<Option<HashMap<(i64, NaiveDate), Totals>>, Error> {
+    use crate::database::schema::statistics;
+
+    if carrier.carrier_ids(conn)?.is_empty() {
+        return Ok(None);
+    }
+
+    let filter = StatisticsFilter {
+        carrier: Some(carrier.clone()),
+        airport: filter.airport.clone(),
+        year: filter.year,
+        month: filter.month,
+        from: filter.from,
+        to: filter.to,
+    };
+    let rows = match filter.query(conn)? {
+        Some(query) => query
+            .select((
+                statistics::airport_id,
+                statistics::time,
+                statistics::flights_total,
+                statistics::flights_on_time,
+                statistics::minutes_delayed_carrier,
+                statistics::minutes_delayed_late_aircraft,
+                statistics::minutes_delayed_national_aviation_system,
+                statistics::minutes_delayed_security,
+                statistics::minutes_delayed_weather,
+            ))
+            .load::<(i64, NaiveDate, i64, i64, i64, i64, i64, i64, i64)>(conn)?,
+        None => Vec::new(),
+    };
+
+    let mut totals = HashMap::new();
+    for (airport_id, time, total, on_time, carrier, late, national, security, weather) in rows {
+        *totals
+            .entry((airport_id, time))
+            .or_insert_with(Totals::default) += Totals {
+            flights_total: total,
+            flights_on_time: on_time,
+            minutes_delayed: [carrier, late, national, security, weather],
+        };
+    }
+    Ok(Some(totals))
+}
+
+/// Compare the carriers identified by `a` and `b` at each airport in each
+/// month where both have statistics matching the `filter`, the carrier of the
+/// filter being replaced by each of them. The rows are ordered by airport code
+/// and month, and are preceded by the comparison over all of them. `None` if
+/// either carrier does not identify anything.
+pub fn compare_carriers(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    a: &Identifier,
+    b: &Identifier,
+) -> Result<Option<CarrierComparison>, Error> {
+    let totals_a = load_totals(conn, filter, a)?;
+    let totals_b = load_totals(conn, filter, b)?;
+    let (totals_a, totals_b) = match (totals_a, totals_b) {
+        (Some(totals_a), Some(totals_b)) => (totals_a, totals_b),
+        _ => return Ok(None),
+    };
+
+    let shared: Vec<((i64, NaiveDate), Totals, Totals)> = totals_a
+        .into_iter()
+        .filter_map(|(key, a)| totals_b.get(&key).map(|b| (key, a, *b)))
+        .collect();
+
+    let airport_ids: Vec<i64> = shared
+        .iter()
+        .map(|((airport_id, _), _, _)| *airport_id)
+        .collect();
+    let airport_codes: HashMap<i64, String> = schema::airports::table
+        .filter(schema::airports::id.eq(any(airport_ids)))
+        .select((schema::airports::id, schema::airports::code))
+        .load::<(i64, String)>(conn)?
+        .into_iter()
+        .collect();
+
+    let mut overall = (Totals::default(), Totals::default());
+    let mut rows = BTreeMap::new();
+    for ((airport_id, time), a, b) in shared {
+        overall.0 += a;
+        overall.1 += b;
+        let code = airport_codes.get(&airport_id).cloned().unwrap_or_default();
+        rows.insert(
+            (code.clone(), airport_id, time),
+            compare(
+                Some(airport_id),
+                Some(code),
+                Some(YearMonth::from(time)),
+                a,
+                b,
+            ),
+        );
+    }
+
+    Ok(Some(CarrierComparison {
+        a: a.to_string(),
+        b: b.to_string(),
+        overall: compare(None, None, None, overall.0, overall.1),
+        rows: rows.into_iter().map(|(_, row)| row).collect(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn totals(flights_total: i64, flights_on_time: i64) -> Totals {
+        Totals {
+            flights_total,
+            flights_on_time,
+            minutes_delayed: [0; 5],
+        }
+    }
+
+    #[test]
+    fn normal_cdf_of_known_quantiles() {
+        assert!((normal_cdf(0.0) - 0.5).abs() < 1e-7);
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-4);
+        assert!((normal_cdf(-1.96) - 0.025).abs() < 1e-4);
+        assert!((normal_cdf(1.0) + normal_cdf(-1.0) - 1.0).abs() < 1e-7);
+    }
+
+    #[test]
+    fn compare_tests_the_difference_of_the_on_time_rates() {
+        let row = compare(None, None, None, totals(100, 80), totals(100, 70));
+
+        assert!((row.on_time_rate_difference.unwrap() - 0.1).abs() < 1e-9);
+        assert!((row.z.unwrap() - 1.632_993).abs() < 1e-6);
+        assert!((row.p_value.unwrap() - 0.102_470).abs() < 1e-4);
+        assert_eq!(row.significant, Some(false));
+    }
+
+    #[test]
+    fn compare_does_not_test_without_flights() {
+        let row = compare(None, None, None, totals(100, 80), totals(0, 0));
+
+        assert_eq!(row.on_time_rate_difference, None);
+        assert_eq!(row.z, None);
+        assert_eq!(row.p_value, None);
+        assert_eq!(row.significant, None);
+    }
+
+    #[test]
+    fn compare_does_not_test_when_every_flight_is_on_time() {
+        let row = compare(None, None, None, totals(100, 100), totals(50, 50));
+
+        assert_eq!(row.on_time_rate_difference, Some(0.0));
+        assert_eq!(row.z, None);
+    }
+}
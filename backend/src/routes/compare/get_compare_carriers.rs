@@ -0,0 +1,195 @@
+//! A module dealing with the `get_compare_carriers` routes.
+
+use super::comparison::compare_carriers;
+use super::views::{CarrierComparison, CarrierPerformance, ComparisonRow};
+
+use crate::routes::parameters::{check_identifier, join_query, Identifier, ParameterError};
+use crate::routes::statistics::filter::{StatisticsFilter, StatisticsFilterForm};
+use crate::CorgisDbConn;
+use diesel::result::Error;
+use rocket::{get, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+use serde::Serialize;
+
+/// Check the `a` and `b` parameters of a request, both must be given and be an
+/// id or a code.
+fn check_parameters(
+    a: Option<Result<Identifier, ParameterError>>,
+    b: Option<Result<Identifier, ParameterError>>,
+) -> Result<(Identifier, Identifier), ParameterError> {
+    Ok((check_identifier(a, "a")?, check_identifier(b, "b")?))
+}
+
+/// Get the JSON representation of the comparison of the carriers `a` and `b`
+/// at the airports and in the months they share.
+#[get("/carriers?<a>&<b>&<filter..>", format = "application/json", rank = 1)]
+pub fn get_compare_carriers_json(
+    conn: CorgisDbConn,
+    a: Option<Result<Identifier, ParameterError>>,
+    b: Option<Result<Identifier, ParameterError>>,
+    filter: LenientForm<StatisticsFilterForm>,
+) -> Result<Result<Option<Json<CarrierComparison>>, ParameterError>, Error> {
+    let (a, b) = match check_parameters(a, b) {
+        Ok(carriers) => carriers,
+        Err(error) => return Ok(Err(error)),
+    };
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    Ok(Ok(compare_carriers(&conn, &filter, &a, &b)?.map(Json)))
+}
+
+/// Get the CSV representation of the comparison of the carriers `a` and `b`
+/// at the airports and in the months they share. Each row holds an airport
+/// and month side by side, preceded by the row over all of them, which has no
+/// airport and month.
+#[get("/carriers?<a>&<b>&<filter..>", format = "text/csv", rank = 2)]
+pub fn get_compare_carriers_csv(
+    conn: CorgisDbConn,
+    a: Option<Result<Identifier, ParameterError>>,
+    b: Option<Result<Identifier, ParameterError>>,
+    filter: LenientForm<StatisticsFilterForm>,
+) -> Result<Result<Option<Csv<CarrierComparison>>, ParameterError>, Error> {
+    fn convertor(comparison: &CarrierComparison) -> String {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            airport_id: Option<i64>,
+            airport_code: Option<&'a str>,
+            month: Option<String>,
+            a_flights_total: i64,
+            a_flights_on_time: i64,
+            a_on_time_rate: Option<f64>,
+            a_minutes_delayed_total: i64,
+            a_share_carrier: Option<f64>,
+            a_share_late_aircraft: Option<f64>,
+            a_share_national_aviation_system: Option<f64>,
+            a_share_security: Option<f64>,
+            a_share_weather: Option<f64>,
+            b_flights_total: i64,
+            b_flights_on_time: i64,
+            b_on_time_rate: Option<f64>,
+            b_minutes_delayed_total: i64,
+            b_share_carrier: Option<f64>,
+            b_share_late_aircraft: Option<f64>,
+            b_share_national_aviation_system: Option<f64>,
+            b_share_security: Option<f64>,
+            b_share_weather: Option<f64>,
+            on_time_rate_difference: Option<f64>,
+            z: Option<f64>,
+            p_value: Option<f64>,
+            significant: Option<bool>,
+        }
+
+        fn record(row: &ComparisonRow) -> Record {
+            let (a, b): (&CarrierPerformance, &CarrierPerformance) = (&row.a, &row.b);
+            Record {
+                airport_id: row.airport_id,
+                airport_code: row.airport_code.as_ref().map(String::as_str),
+                month: row.month.map(|month| month.to_string()),
+                a_flights_total: a.flights_total,
+                a_flights_on_time: a.flights_on_time,
+                a_on_time_rate: a.on_time_rate,
+                a_minutes_delayed_total: a.minutes_delayed_total,
+                a_share_carrier: a.share_carrier,
+                a_share_late_aircraft: a.share_late_aircraft,
+                a_share_national_aviation_system: a.share_national_aviation_system,
+                a_share_security: a.share_security,
+                a_share_weather: a.share_weather,
+                b_flights_total: b.flights_total,
+                b_flights_on_time: b.flights_on_time,
+                b_on_time_rate: b.on_time_rate,
+                b_minutes_delayed_total: b.minutes_delayed_total,
+                b_share_carrier: b.share_carrier,
+                b_share_late_aircraft: b.share_late_aircraft,
+                b_share_national_aviation_system: b.share_national_aviation_system,
+                b_share_security: b.share_security,
+                b_share_weather: b.share_weather,
+                on_time_rate_difference: row.on_time_rate_difference,
+                z: row.z,
+                p_value: row.p_value,
+                significant: row.significant,
+            }
+        }
+
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        wtr.serialize(record(&comparison.overall)).unwrap();
+        for row in &comparison.rows {
+            wtr.serialize(record(row)).unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let (a, b) = match check_parameters(a, b) {
+        Ok(carriers) => carriers,
+        Err(error) => return Ok(Err(error)),
+    };
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    Ok(Ok(
+        compare_carriers(&conn, &filter, &a, &b)?.map(|data| Csv(data, convertor))
+    ))
+}
+
+/// Get the HAL representation of the comparison of the carriers `a` and `b`
+/// at the airports and in the months they share.
+#[get(
+    "/carriers?<a>&<b>&<filter..>",
+    format = "application/hal+json",
+    rank = 3
+)]
+pub fn get_compare_carriers_hal(
+    conn: CorgisDbConn,
+    a: Option<Result<Identifier, ParameterError>>,
+    b: Option<Result<Identifier, ParameterError>>,
+    filter: LenientForm<StatisticsFilterForm>,
+) -> Result<Result<Option<Json<HalResource>>, ParameterError>, Error> {
+    let (a, b) = match check_parameters(a, b) {
+        Ok(carriers) => carriers,
+        Err(error) => return Ok(Err(error)),
+    };
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![format!("a={}&b={}", a, b), filter.to_string()]);
+    let statistics = |carrier: &Identifier| {
+        let filter = StatisticsFilter {
+            carrier: Some(carrier.clone()),
+            airport: filter.airport.clone(),
+            year: filter.year,
+            month: filter.month,
+            from: filter.from,
+            to: filter.to,
+        };
+        format!("/statistics?{}", filter)
+    };
+    Ok(Ok(compare_carriers(&conn, &filter, &a, &b)?.map(|data| {
+        Json(
+            HalResource::new(&data)
+                .with_link("self", format!("/compare/carriers?{}", query))
+                .with_link("statistics_a", statistics(&a))
+                .with_link("statistics_b", statistics(&b)),
+        )
+    })))
+}
+
+/// Get the default representation of the comparison of the carriers `a` and
+/// `b` at the airports and in the months they share. This is executed if the
+/// other routes are not matched.
+#[get("/carriers?<a>&<b>&<filter..>", rank = 4)]
+pub fn get_compare_carriers_default(
+    conn: CorgisDbConn,
+    a: Option<Result<Identifier, ParameterError>>,
+    b: Option<Result<Identifier, ParameterError>>,
+    filter: LenientForm<StatisticsFilterForm>,
+) -> Result<Result<Option<Json<CarrierComparison>>, ParameterError>, diesel::result::Error> {
+    get_compare_carriers_json(conn, a, b, filter)
+}
@@ -0,0 +1,20 @@
+//! A module dealing with the "/compare" routes.
+
+mod comparison;
+mod get_compare_carriers;
+mod views;
+
+use rocket::routes;
+
+/// Mount the compare routes to the provided and `rocket` and return the resulting `rocket`.
+pub fn mount(rocket: rocket::Rocket) -> rocket::Rocket {
+    rocket.mount(
+        "/compare",
+        routes!(
+            get_compare_carriers::get_compare_carriers_json,
+            get_compare_carriers::get_compare_carriers_csv,
+            get_compare_carriers::get_compare_carriers_hal,
+            get_compare_carriers::get_compare_carriers_default,
+        ),
+    )
+}
@@ -0,0 +1,84 @@
+//! A module containing the comparison structures returned by the API.
+
+use crate::routes::parameters::YearMonth;
+use serde::Serialize;
+
+/// The comparison of 2 carriers at the airports and in the months they share,
+/// as returned by the API.
+#[derive(Serialize)]
+pub struct CarrierComparison {
+    /// The first carrier, as identified in the request.
+    pub a: String,
+
+    /// The second carrier, as identified in the request.
+    pub b: String,
+
+    /// The comparison over every airport and month shared.
+    pub overall: ComparisonRow,
+
+    /// The comparison at each airport in each month shared.
+    pub rows: Vec<ComparisonRow>,
+}
+
+/// The comparison of 2 carriers at an airport in a month, or overall.
+#[derive(Serialize)]
+pub struct ComparisonRow {
+    /// The id of the airport, null overall.
+    pub airport_id: Option<i64>,
+
+    /// The code of the airport, null overall.
+    pub airport_code: Option<String>,
+
+    /// The month, formatted as `YYYY-MM`, null overall.
+    pub month: Option<YearMonth>,
+
+    /// The performance of the first carrier.
+    pub a: CarrierPerformance,
+
+    /// The performance of the second carrier.
+    pub b: CarrierPerformance,
+
+    /// The on-time rate of the first carrier less that of the second.
+    pub on_time_rate_difference: Option<f64>,
+
+    /// The statistic of the two-proportion z-test of the on-time rates.
+    pub z: Option<f64>,
+
+    /// The two-sided p-value of the z-test.
+    pub p_value: Option<f64>,
+
+    /// Whether the difference is significant at the 5% level.
+    pub significant: Option<bool>,
+}
+
+/// The performance of a carrier at an airport in a month, or overall.
+#[derive(Serialize)]
+pub struct CarrierPerformance {
+    /// The total number of flights.
+    pub flights_total: i64,
+
+    /// The number of flights on time.
+    pub flights_on_time: i64,
+
+    /// The ratio of the flights on time to the total flights, if there are any
+    /// flights.
+    pub on_time_rate: Option<f64>,
+
+    /// The total number of minutes delayed.
+    pub minutes_delayed_total: i64,
+
+    /// The share of the minutes delayed due to the carrier.
+    pub share_carrier: Option<f64>,
+
+    /// The share of the minutes delayed due to late aircraft.
+    pub share_late_aircraft: Option<f64>,
+
+    /// The share of the minutes delayed due to the national aviation system.
+    pub share_national_aviation_system: Option<f64>,
+
+    /// The share of the minutes delayed due to security.
+    pub share_security: Option<f64>,
+
+    /// The share of the minutes delayed due to the weather.
+    pub share_weather: Option<f64>,
+}
@@ -0,0 +1,137 @@
+//! A module dealing with the `get_coverage` routes.
+
+use super::matrix::{load_coverage, Pivot};
+use super::views::Coverage;
+
+use crate::routes::parameters::{Identifier, ParameterError};
+use crate::routes::statistics::filter::{StatisticsFilter, StatisticsFilterForm};
+use crate::CorgisDbConn;
+use diesel::result::Error;
+use rayon::prelude::*;
+use rocket::{get, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+use std::collections::HashMap;
+
+/// Get the JSON representation of the carriers serving each airport.
+#[get("/?<filter..>", format = "application/json", rank = 1)]
+pub fn get_coverage_json(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+) -> Result<Result<Json<Coverage>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    load_coverage(&conn, &filter).map(|data| Ok(Json(data)))
+}
+
+/// Get the CSV representation of the carriers serving each airport, pivoted
+/// with a row for each airport and a column for each carrier. Each cell holds
+/// the value given by `pivot`, and is empty where the carrier does not serve
+/// the airport.
+#[get("/?<pivot>&<filter..>", format = "text/csv", rank = 2)]
+pub fn get_coverage_csv(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    pivot: Option<Result<Pivot, ParameterError>>,
+) -> Result<Result<Csv<(Coverage, Pivot)>, ParameterError>, Error> {
+    fn convertor((coverage, pivot): &(Coverage, Pivot)) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        // Carrier codes are not unique, so each column is headed by the code
+        // and the name of its carrier.
+        let mut header = vec![String::from("airport")];
+        header.extend(
+            coverage
+                .carriers
+                .iter()
+                .map(|carrier| format!("{} ({})", carrier.code, carrier.name)),
+        );
+        wtr.write_record(&header).unwrap();
+
+        let cells: HashMap<(&str, i64), String> = coverage
+            .cells
+            .iter()
+            .map(|cell| {
+                (
+                    (cell.airport_code.as_str(), cell.carrier_id),
+                    pivot.value(cell),
+                )
+            })
+            .collect();
+        for airport in &coverage.airports {
+            let mut record = vec![airport.clone()];
+            record.extend(coverage.carriers.iter().map(|carrier| {
+                cells
+                    .get(&(airport.as_str(), carrier.id))
+                    .cloned()
+                    .unwrap_or_default()
+            }));
+            wtr.write_record(&record).unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let pivot = match pivot.transpose() {
+        Ok(pivot) => pivot.unwrap_or_default(),
+        Err(error) => return Ok(Err(error)),
+    };
+
+    load_coverage(&conn, &filter).map(|data| Ok(Csv((data, pivot), convertor)))
+}
+
+/// Get the HAL representation of the carriers serving each airport. Each cell
+/// links to its airport, its carrier and their statistics.
+#[get("/?<filter..>", format = "application/hal+json", rank = 3)]
+pub fn get_coverage_hal(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let data = load_coverage(&conn, &filter)?;
+    let cells = data
+        .cells
+        .par_iter()
+        .map(|cell| {
+            let statistics = StatisticsFilter {
+                carrier: Some(Identifier::Id(cell.carrier_id)),
+                airport: Some(Identifier::Id(cell.airport_id)),
+                year: filter.year,
+                month: filter.month,
+                from: filter.from,
+                to: filter.to,
+            };
+            HalResource::new(cell)
+                .with_link("airport", format!("/airports/{}", cell.airport_id))
+                .with_link("carrier", format!("/carriers/{}", cell.carrier_id))
+                .with_link("statistics", format!("/statistics?{}", statistics))
+        })
+        .collect();
+
+    let result = HalResource::new(())
+        .with_link("self", format!("/coverage?{}", filter))
+        .with_resources("cells", cells);
+
+    Ok(Ok(Json(result)))
+}
+
+/// Get the default representation of the carriers serving each airport. This
+/// is executed if the other routes are not matched.
+#[get("/?<filter..>", rank = 4)]
+pub fn get_coverage_default(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+) -> Result<Result<Json<Coverage>, ParameterError>, Error> {
+    get_coverage_json(conn, filter)
+}
@@ -0,0 +1,171 @@
+//! A module providing the matrix of the carriers serving each airport.
+
+use super::views::{Coverage, CoverageCarrier, CoverageCell};
+
+use crate::database::schema;
+use crate::routes::parameters::{ParameterError, YearMonth};
+use crate::routes::statistics::filter::{filtered_join, sum_int8, StatisticsFilter};
+use chrono::NaiveDate;
+use diesel::{
+    dsl::sql,
+    prelude::*,
+    result::Error,
+    sql_types::{BigInt, Date, Text},
+};
+use rocket::{http::RawStr, request::FromFormValue};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The value of each cell of the CSV pivot, as given by the `pivot` parameter,
+/// either `flights_total`, `months`, `first_month` or `last_month`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pivot {
+    /// The total number of flights.
+    FlightsTotal,
+
+    /// The number of months with statistics.
+    Months,
+
+    /// The first month with statistics.
+    FirstMonth,
+
+    /// The last month with statistics.
+    LastMonth,
+}
+
+impl Default for Pivot {
+    /// The total number of flights.
+    fn default() -> Self {
+        Pivot::FlightsTotal
+    }
+}
+
+impl<'v> FromFormValue<'v> for Pivot {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "flights_total" => Ok(Pivot::FlightsTotal),
+            "months" => Ok(Pivot::Months),
+            "first_month" => Ok(Pivot::FirstMonth),
+            "last_month" => Ok(Pivot::LastMonth),
+            _ => Err(ParameterError::new(
+                "pivot",
+                format!(
+                    "unknown pivot `{}`, expected flights_total, months, first_month or \
+                     last_month",
+                    form_value
+                ),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Pivot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pivot::FlightsTotal => write!(f, "flights_total"),
+            Pivot::Months => write!(f, "months"),
+            Pivot::FirstMonth => write!(f, "first_month"),
+            Pivot::LastMonth => write!(f, "last_month"),
+        }
+    }
+}
+
+impl Pivot {
+    /// The value of the `cell` in the pivot.
+    pub fn value(self, cell: &CoverageCell) -> String {
+        match self {
+            Pivot::FlightsTotal => cell.flights_total.to_string(),
+            Pivot::Months => cell.months.to_string(),
+            Pivot::FirstMonth => cell.first_month.to_string(),
+            Pivot::LastMonth => cell.last_month.to_string(),
+        }
+    }
+}
+
+/// Load the coverage of the airports by the carriers in the statistics
+/// matching the `filter`. The cells are summed by the database, a carrier
+/// serving an airport if it has statistics there.
+pub fn load_coverage(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+) -> Result<Coverage, Error> {
+    let rows = match filtered_join(conn, filter)? {
+        Some(query) => query
+            .select((
+                schema::airports::id,
+                schema::airports::code,
+                schema::carriers::id,
+                schema::carriers::code,
+                schema::carriers::name,
+                sql::<Date>("MIN(statistics.time)"),
+                sql::<Date>("MAX(statistics.time)"),
+                sql::<BigInt>("COUNT(DISTINCT statistics.time)"),
+                sum_int8("flights_total"),
+            ))
+            .group_by(sql::<BigInt>(
+                "airports.id, airports.code, carriers.id, carriers.code, carriers.name",
+            ))
+            .order(sql::<Text>(
+                "airports.code, airports.id, carriers.code, carriers.id",
+            ))
+            .load::<(
+                i64,
+                String,
+                i64,
+                String,
+                String,
+                NaiveDate,
+                NaiveDate,
+                i64,
+                i64,
+            )>(conn)?,
+        None => Vec::new(),
+    };
+
+    let mut airports = Vec::new();
+    let mut carriers = BTreeMap::new();
+    let cells = rows
+        .into_iter()
+        .map(
+            |(
+                airport_id,
+                airport_code,
+                carrier_id,
+                carrier_code,
+                carrier_name,
+                first,
+                last,
+                months,
+                flights,
+            )| {
+                if airports.last() != Some(&airport_code) {
+                    airports.push(airport_code.clone());
+                }
+                carriers
+                    .entry((carrier_code.clone(), carrier_id))
+                    .or_insert(carrier_name);
+                CoverageCell {
+                    airport_id,
+                    airport_code,
+                    carrier_id,
+                    carrier_code,
+                    first_month: YearMonth::from(first),
+                    last_month: YearMonth::from(last),
+                    months,
+                    flights_total: flights,
+                }
+            },
+        )
+        .collect();
+
+    Ok(Coverage {
+        airports,
+        carriers: carriers
+            .into_iter()
+            .map(|((code, id), name)| CoverageCarrier { id, code, name })
+            .collect(),
+        cells,
+    })
+}
@@ -0,0 +1,20 @@
+//! A module dealing with the "/coverage" routes.
+
+mod get_coverage;
+mod matrix;
+mod views;
+
+use rocket::routes;
+
+/// Mount the coverage routes to the provided and `rocket` and return the resulting `rocket`.
+pub fn mount(rocket: rocket::Rocket) -> rocket::Rocket {
+    rocket.mount(
+        "/coverage",
+        routes!(
+            get_coverage::get_coverage_json,
+            get_coverage::get_coverage_csv,
+            get_coverage::get_coverage_hal,
+            get_coverage::get_coverage_default,
+        ),
+    )
+}
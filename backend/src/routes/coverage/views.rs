@@ -0,0 +1,62 @@
+//! A module containing the coverage structures returned by the API.
+
+use crate::routes::parameters::YearMonth;
+use serde::Serialize;
+
+/// The carriers serving each airport as returned by the API.
+#[derive(Serialize)]
+pub struct Coverage {
+    /// The codes of the airports, the rows of the matrix, in order.
+    pub airports: Vec<String>,
+
+    /// The carriers, the columns of the matrix, ordered by code and id.
+    pub carriers: Vec<CoverageCarrier>,
+
+    /// The cells of the matrix where a carrier serves an airport, ordered by
+    /// airport code and carrier code and id. The other cells are left out.
+    pub cells: Vec<CoverageCell>,
+}
+
+/// A carrier serving any of the airports. Carrier codes are not unique, so a
+/// column is identified by the id of its carrier.
+#[derive(Serialize)]
+pub struct CoverageCarrier {
+    /// The id of the carrier.
+    pub id: i64,
+
+    /// The code of the carrier.
+    pub code: String,
+
+    /// The name of the carrier.
+    pub name: String,
+}
+
+/// The presence of a carrier at an airport.
+#[derive(Serialize)]
+pub struct CoverageCell {
+    /// The id of the airport.
+    pub airport_id: i64,
+
+    /// The code of the airport.
+    pub airport_code: String,
+
+    /// The id of the carrier.
+    pub carrier_id: i64,
+
+    /// The code of the carrier.
+    pub carrier_code: String,
+
+    /// The first month with statistics of the carrier at the airport,
+    /// formatted as `YYYY-MM`.
+    pub first_month: YearMonth,
+
+    /// The last month with statistics of the carrier at the airport, formatted
+    /// as `YYYY-MM`.
+    pub last_month: YearMonth,
+
+    /// The number of months with statistics of the carrier at the airport.
+    pub months: i64,
+
+    /// The total number of flights of the carrier at the airport.
+    pub flights_total: i64,
+}
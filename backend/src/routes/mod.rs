@@ -1,7 +1,13 @@
 //! A module dealing with the routes served by the web-server.
 
 mod airports;
+mod analytics;
 mod carriers;
+mod compare;
+mod coverage;
+mod pagination;
+mod parameters;
+mod rankings;
 mod statistics;
 
 /// Mount the routes to the provided and `rocket` and return the resulting `rocket`.
@@ -12,6 +18,14 @@ pub fn mount(rocket: rocket::Rocket) -> rocket::Rocket {
     let rocket = carriers::mount(rocket);
     // Stage 03: Mount statistics
     let rocket = statistics::mount(rocket);
+    // Stage 04: Mount rankings
+    let rocket = rankings::mount(rocket);
+    // Stage 05: Mount analytics
+    let rocket = analytics::mount(rocket);
+    // Stage 06: Mount compare
+    let rocket = compare::mount(rocket);
+    // Stage 07: Mount coverage
+    let rocket = coverage::mount(rocket);
 
     // Return the rocket
     rocket
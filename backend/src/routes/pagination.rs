@@ -0,0 +1,351 @@
+//! A module providing the pagination of the collections served by the routes.
+
+use crate::routes::parameters::ParameterError;
+use chrono::NaiveDate;
+use rocket::{
+    http::RawStr,
+    request::{FromFormValue, Request},
+    response::{self, Responder, Response},
+};
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// The number of items in a page if no limit is given.
+const LIMIT: usize = 100;
+
+/// The most items a page may have.
+const MAX_LIMIT: usize = 1000;
+
+/// The position of an item within a collection ordered by time and id, used
+/// to continue a collection after that item. To clients a cursor is an opaque
+/// string.
+#[derive(Clone, Copy, Debug)]
+pub struct Cursor {
+    /// The time of the item.
+    pub time: NaiveDate,
+
+    /// The id of the item.
+    pub id: i64,
+}
+
+impl<'v> FromFormValue<'v> for Cursor {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let error = || ParameterError::new("cursor", format!("`{}` is not a cursor", form_value));
+
+        let value = form_value.as_str();
+        if value.len() != 24 || !value.is_ascii() {
+            return Err(error());
+        }
+
+        let days = u32::from_str_radix(&value[..8], 16).map_err(|_| error())?;
+        let id = u64::from_str_radix(&value[8..], 16).map_err(|_| error())?;
+        // Chrono overflows on the largest numbers of days rather than
+        // rejecting them.
+        let days = days as i32;
+        if days > i32::max_value() - 365 {
+            return Err(error());
+        }
+        match NaiveDate::from_num_days_from_ce_opt(days) {
+            Some(time) => Ok(Cursor {
+                time,
+                id: id as i64,
+            }),
+            None => Err(error()),
+        }
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use chrono::Datelike;
+
+        write!(
+            f,
+            "{:08x}{:016x}",
+            self.time.num_days_from_ce() as u32,
+            self.id as u64
+        )
+    }
+}
+
+impl Serialize for Cursor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// A page of a collection as requested by the `limit`, `offset` and `cursor`
+/// parameters of a request.
+#[derive(Clone, Copy, Debug)]
+pub struct PageRequest {
+    /// The maximum number of items in the page.
+    pub limit: i64,
+
+    /// The number of items preceding the page, if the page is not requested by
+    /// a cursor.
+    pub offset: i64,
+
+    /// The position after which the page starts, if any.
+    pub cursor: Option<Cursor>,
+}
+
+impl PageRequest {
+    /// Check the `limit`, `offset` and `cursor` parameters of a request, the
+    /// limit is at most 1000 items and the offset must fit the database. The
+    /// page is the first 100 items if none of them is given.
+    pub fn check(
+        limit: Option<Result<usize, &RawStr>>,
+        offset: Option<Result<usize, &RawStr>>,
+        cursor: Option<Result<Cursor, ParameterError>>,
+    ) -> Result<Self, ParameterError> {
+        let limit = match limit {
+            None => LIMIT,
+            Some(Ok(limit)) if limit <= MAX_LIMIT => limit,
+            Some(_) => {
+                return Err(ParameterError::new(
+                    "limit",
+                    format!("the limit must be a number between 0 and {}", MAX_LIMIT),
+                ))
+            }
+        };
+        let offset = match offset {
+            None => 0,
+            Some(Ok(offset)) if offset <= i64::max_value() as usize => offset,
+            Some(_) => {
+                return Err(ParameterError::new(
+                    "offset",
+                    format!(
+                        "the offset must be a number between 0 and {}",
+                        i64::max_value()
+                    ),
+                ))
+            }
+        };
+
+        Ok(PageRequest {
+            limit: limit as i64,
+            offset: offset as i64,
+            cursor: cursor.transpose()?,
+        })
+    }
+}
+
+/// An item of a collection ordered by time and id.
+pub trait Positioned {
+    /// The cursor at the position of the item.
+    fn cursor(&self) -> Cursor;
+}
+
+/// The position of a page within a collection.
+#[derive(Clone, Copy, Serialize)]
+pub struct Pagination {
+    /// The number of items in the whole collection.
+    pub total: i64,
+
+    /// The maximum number of items in a page.
+    pub limit: i64,
+
+    /// The number of items preceding the page, if the page was requested by
+    /// its offset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+
+    /// The position after which the page starts, if the page was requested by
+    /// a cursor.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<Cursor>,
+
+    /// The cursor of the next page, if there is one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<Cursor>,
+}
+
+impl Pagination {
+    /// The positions of the pages related to this page, as query parameters,
+    /// by the name of the relation. The previous and next pages are omitted
+    /// when they do not exist. A page requested by a cursor only leads to the
+    /// first and next pages, as the preceding items are not known.
+    fn relations(&self) -> Vec<(&'static str, String)> {
+        if let Some(cursor) = self.cursor {
+            let mut relations = vec![
+                ("self", format!("cursor={}", cursor)),
+                ("first", String::from("offset=0")),
+            ];
+            if let Some(next_cursor) = self.next_cursor {
+                relations.push(("next", format!("cursor={}", next_cursor)));
+            }
+            return relations;
+        }
+
+        let offset = self.offset.unwrap_or(0);
+        let mut relations = vec![
+            ("self", format!("offset={}", offset)),
+            ("first", String::from("offset=0")),
+        ];
+        if self.limit > 0 {
+            if offset > 0 {
+                relations.push(("prev", format!("offset={}", (offset - self.limit).max(0))));
+            }
+            let next = offset.saturating_add(self.limit);
+            if next < self.total {
+                relations.push(("next", format!("offset={}", next)));
+            }
+            relations.push((
+                "last",
+                format!(
+                    "offset={}",
+                    (self.total - 1).max(0) / self.limit * self.limit
+                ),
+            ));
+        }
+        relations
+    }
+
+    /// The links to the pages related to this page. The `path` is that of the
+    /// collection and the `query` holds any other parameters of the request,
+    /// formatted as in a URI.
+    pub fn links(&self, path: &str, query: &str) -> Vec<(&'static str, String)> {
+        let separator = if query.is_empty() { "" } else { "&" };
+        self.relations()
+            .into_iter()
+            .map(|(relation, position)| {
+                let href = format!(
+                    "{}?{}{}limit={}&{}",
+                    path, query, separator, self.limit, position
+                );
+                (relation, href)
+            })
+            .collect()
+    }
+
+    /// Build the HAL representation of the page, embedding the `items` under
+    /// `name` and linking to the related pages.
+    pub fn hal(&self, path: &str, query: &str, name: &str, items: Vec<HalResource>) -> HalResource {
+        self.links(path, query).into_iter().fold(
+            HalResource::new(self).with_resources(name, items),
+            |resource, (relation, href)| resource.with_link(relation, href),
+        )
+    }
+
+    /// Build the CSV representation of the page, the pagination is described
+    /// by the `X-Total-Count` and `Link` headers.
+    pub fn csv<T>(&self, path: &str, query: &str, csv: Csv<T>) -> PagedCsv<T> {
+        let link = self
+            .links(path, query)
+            .into_iter()
+            .filter(|(relation, _)| *relation != "self")
+            .map(|(relation, href)| format!("<{}>; rel=\"{}\"", href, relation))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        PagedCsv {
+            csv,
+            total: self.total,
+            link,
+        }
+    }
+}
+
+/// A page of a collection.
+#[derive(Serialize)]
+pub struct Page<T> {
+    /// The position of the page within the collection.
+    #[serde(flatten)]
+    pub pagination: Pagination,
+
+    /// The items of the page.
+    pub items: Vec<T>,
+}
+
+/// A page of a collection represented as CSV, with the pagination in the
+/// headers of the response.
+pub struct PagedCsv<T> {
+    csv: Csv<T>,
+    total: i64,
+    link: String,
+}
+
+impl<'r, T> Responder<'r> for PagedCsv<T> {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        Response::build_from(self.csv.respond_to(request)?)
+            .raw_header("X-Total-Count", self.total.to_string())
+            .raw_header("Link", self.link)
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_round_trips() {
+        let cursor = Cursor {
+            time: NaiveDate::from_ymd(2010, 6, 1),
+            id: 438_123_234_994_716_673,
+        };
+
+        let decoded = Cursor::from_form_value(RawStr::from_str(&cursor.to_string())).unwrap();
+
+        assert_eq!(decoded.time, cursor.time);
+        assert_eq!(decoded.id, cursor.id);
+    }
+
+    #[test]
+    fn cursor_is_24_hexadecimal_digits() {
+        let cursor = Cursor {
+            time: NaiveDate::from_ymd(1, 1, 1),
+            id: 255,
+        };
+
+        assert_eq!(cursor.to_string(), "0000000100000000000000ff");
+    }
+
+    #[test]
+    fn malformed_cursors_are_rejected() {
+        for value in &[
+            "",
+            "0000000100000000000000f",
+            "0000000100000000000000fff",
+            "0000000100000000000000fg",
+            "7fffffff00000000000000ff",
+            "8000000000000000000000ff",
+        ] {
+            let error = Cursor::from_form_value(RawStr::from_str(value)).unwrap_err();
+            assert_eq!(error.parameter, "cursor");
+        }
+    }
+
+    #[test]
+    fn page_request_defaults_to_the_first_100_items() {
+        let page = PageRequest::check(None, None, None).unwrap();
+
+        assert_eq!((page.limit, page.offset), (100, 0));
+        assert!(page.cursor.is_none());
+    }
+
+    #[test]
+    fn page_request_rejects_a_bad_cursor() {
+        let cursor = Some(Err(ParameterError::new("cursor", "not a cursor")));
+
+        let error = PageRequest::check(None, None, cursor).unwrap_err();
+
+        assert_eq!(error.parameter, "cursor");
+    }
+
+    #[test]
+    fn page_request_rejects_a_limit_or_offset_out_of_range() {
+        let error = PageRequest::check(Some(Ok(MAX_LIMIT + 1)), None, None).unwrap_err();
+        assert_eq!(error.parameter, "limit");
+
+        let error = PageRequest::check(None, Some(Ok(usize::max_value())), None).unwrap_err();
+        assert_eq!(error.parameter, "offset");
+
+        let error = PageRequest::check(None, Some(Err(RawStr::from_str("-1"))), None).unwrap_err();
+        assert_eq!(error.parameter, "offset");
+    }
+}
@@ -0,0 +1,231 @@
+//! A module containing the query parameters shared by the routes.
+
+use chrono::NaiveDate;
+use diesel::{prelude::*, result::Error};
+use rocket::{
+    http::RawStr,
+    request::{FromFormValue, Request},
+    response::{self, status::BadRequest, Responder},
+};
+use serde::{Serialize, Serializer};
+use std::fmt;
+
+/// An airport or a carrier as identified in a query parameter, either by its id
+/// or by its code as assigned by the International Air Transport Association.
+/// A value consisting only of digits is an id, any other value is a code.
+#[derive(Clone, Debug)]
+pub enum Identifier {
+    /// The id of an airport or carrier.
+    Id(i64),
+
+    /// The code of an airport or carrier, in uppercase.
+    Code(String),
+}
+
+impl<'v> FromFormValue<'v> for Identifier {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let value = form_value
+            .url_decode()
+            .map_err(|_| ParameterError::new("identifier", "not valid UTF-8"))?;
+        let value = value.trim();
+
+        if value.is_empty() {
+            Err(ParameterError::new(
+                "identifier",
+                "an id or a code is expected",
+            ))
+        } else if let Ok(id) = value.parse::<i64>() {
+            Ok(Identifier::Id(id))
+        } else if value.chars().all(char::is_alphanumeric) {
+            Ok(Identifier::Code(value.to_uppercase()))
+        } else {
+            Err(ParameterError::new(
+                "identifier",
+                format!("`{}` is neither an id nor a code", value),
+            ))
+        }
+    }
+}
+
+impl fmt::Display for Identifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Identifier::Id(id) => write!(f, "{}", id),
+            Identifier::Code(code) => write!(f, "{}", code),
+        }
+    }
+}
+
+impl Identifier {
+    /// Find the ids of the airports matching the identifier. As airport codes
+    /// are unique there is at most one airport.
+    pub fn airport_ids(&self, conn: &diesel::PgConnection) -> Result<Vec<i64>, Error> {
+        use crate::database::schema::airports;
+
+        match self {
+            Identifier::Id(id) => airports::table.find(id).select(airports::id).load(conn),
+            Identifier::Code(code) => airports::table
+                .filter(airports::code.eq(code))
+                .select(airports::id)
+                .load(conn),
+        }
+    }
+
+    /// Find the ids of the carriers matching the identifier. Carrier codes are
+    /// not unique, only the combination of code and name is, so a code matches
+    /// every carrier that has operated under it.
+    pub fn carrier_ids(&self, conn: &diesel::PgConnection) -> Result<Vec<i64>, Error> {
+        use crate::database::schema::carriers;
+
+        match self {
+            Identifier::Id(id) => carriers::table.find(id).select(carriers::id).load(conn),
+            Identifier::Code(code) => carriers::table
+                .filter(carriers::code.eq(code))
+                .select(carriers::id)
+                .load(conn),
+        }
+    }
+}
+
+/// Check the required `parameter` identifying an airport or a carrier, which
+/// must be given and be an id or a code.
+pub fn check_identifier(
+    identifier: Option<Result<Identifier, ParameterError>>,
+    parameter: &'static str,
+) -> Result<Identifier, ParameterError> {
+    match identifier {
+        Some(Ok(identifier)) => Ok(identifier),
+        Some(Err(error)) => Err(ParameterError::new(parameter, error.message)),
+        None => Err(ParameterError::new(
+            parameter,
+            "an id or a code is required",
+        )),
+    }
+}
+
+/// A month of a year as given in a query parameter, formatted as `YYYY-MM`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct YearMonth {
+    /// The year.
+    pub year: i32,
+
+    /// The month of the year, starting at 1.
+    pub month: u32,
+}
+
+impl<'v> FromFormValue<'v> for YearMonth {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let value = form_value
+            .url_decode()
+            .map_err(|_| ParameterError::new("month", "not valid UTF-8"))?;
+        let mut parts = value.trim().splitn(2, '-');
+
+        let year = parts.next().and_then(|year| year.parse::<i32>().ok());
+        let month = parts.next().and_then(|month| month.parse::<u32>().ok());
+        match (year, month) {
+            (Some(year), Some(month)) if NaiveDate::from_ymd_opt(year, month, 1).is_some() => {
+                Ok(YearMonth { year, month })
+            }
+            _ => Err(ParameterError::new(
+                "month",
+                format!("`{}` is not a month formatted as YYYY-MM", value.trim()),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for YearMonth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}", self.year, self.month)
+    }
+}
+
+impl Serialize for YearMonth {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl From<NaiveDate> for YearMonth {
+    /// The month of the `date`.
+    fn from(date: NaiveDate) -> Self {
+        use chrono::Datelike;
+
+        YearMonth {
+            year: date.year(),
+            month: date.month(),
+        }
+    }
+}
+
+impl YearMonth {
+    /// The first day of the month.
+    pub fn first_day(&self) -> NaiveDate {
+        NaiveDate::from_ymd(self.year, self.month, 1)
+    }
+
+    /// The first day of the following month, `None` if it is out of range.
+    pub fn next_first_day(&self) -> Option<NaiveDate> {
+        match self.month {
+            12 => NaiveDate::from_ymd_opt(self.year.checked_add(1)?, 1, 1),
+            month => NaiveDate::from_ymd_opt(self.year, month + 1, 1),
+        }
+    }
+
+    /// The following month, `None` if it is out of range.
+    pub fn next(&self) -> Option<YearMonth> {
+        self.next_first_day().map(YearMonth::from)
+    }
+}
+
+/// An invalid query parameter. As a responder it results in a 400 response
+/// describing the problem.
+#[derive(Debug)]
+pub struct ParameterError {
+    /// The name of the parameter.
+    pub parameter: &'static str,
+
+    /// A description of the problem.
+    pub message: String,
+}
+
+impl ParameterError {
+    /// Create a `ParameterError` for the `parameter`.
+    pub fn new<S: Into<String>>(parameter: &'static str, message: S) -> Self {
+        ParameterError {
+            parameter,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "invalid `{}` parameter: {}",
+            self.parameter, self.message
+        )
+    }
+}
+
+impl<'r> Responder<'r> for ParameterError {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        BadRequest(Some(self.to_string())).respond_to(request)
+    }
+}
+
+/// Join query parameters, each formatted as `name=value` or as several such
+/// parameters joined by `&`, into the query of a URI. Empty parameters are
+/// skipped.
+pub fn join_query(parameters: Vec<String>) -> String {
+    parameters
+        .into_iter()
+        .filter(|parameter| !parameter.is_empty())
+        .collect::<Vec<String>>()
+        .join("&")
+}
@@ -0,0 +1,138 @@
+//! A module dealing with the `get_rankings_airports` routes.
+
+use super::ranking::{load_rankings, Measure, Order, RankingParameters};
+use super::views::Ranking;
+
+use crate::routes::parameters::{join_query, Identifier, ParameterError};
+use crate::routes::statistics::filter::{GroupBy, StatisticsFilter, StatisticsFilterForm};
+use crate::CorgisDbConn;
+use diesel::result::Error;
+use rayon::prelude::*;
+use rocket::{get, http::RawStr, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+
+/// Get the JSON representation of the airports ranked by their performance.
+#[get(
+    "/airports?<by>&<order>&<min_flights>&<filter..>",
+    format = "application/json",
+    rank = 1
+)]
+pub fn get_rankings_airports_json(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    by: Option<Result<Measure, ParameterError>>,
+    order: Option<Result<Order, ParameterError>>,
+    min_flights: Option<Result<i64, &RawStr>>,
+) -> Result<Result<Json<Vec<Ranking>>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match RankingParameters::check(by, order, min_flights) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    load_rankings(&conn, &filter, GroupBy::Airport, &parameters).map(|data| Ok(Json(data)))
+}
+
+/// Get the CSV representation of the airports ranked by their performance.
+#[get(
+    "/airports?<by>&<order>&<min_flights>&<filter..>",
+    format = "text/csv",
+    rank = 2
+)]
+pub fn get_rankings_airports_csv(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    by: Option<Result<Measure, ParameterError>>,
+    order: Option<Result<Order, ParameterError>>,
+    min_flights: Option<Result<i64, &RawStr>>,
+) -> Result<Result<Csv<Vec<Ranking>>, ParameterError>, Error> {
+    fn convertor(rankings: &Vec<Ranking>) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        for ranking in rankings {
+            wtr.serialize(ranking).unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match RankingParameters::check(by, order, min_flights) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    load_rankings(&conn, &filter, GroupBy::Airport, &parameters)
+        .map(|data| Ok(Csv(data, convertor)))
+}
+
+/// Get the HAL representation of the airports ranked by their performance.
+/// Each airport links to itself and to the statistics it is measured by.
+#[get(
+    "/airports?<by>&<order>&<min_flights>&<filter..>",
+    format = "application/hal+json",
+    rank = 3
+)]
+pub fn get_rankings_airports_hal(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    by: Option<Result<Measure, ParameterError>>,
+    order: Option<Result<Order, ParameterError>>,
+    min_flights: Option<Result<i64, &RawStr>>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match RankingParameters::check(by, order, min_flights) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![filter.to_string(), parameters.to_query()]);
+    let rankings = load_rankings(&conn, &filter, GroupBy::Airport, &parameters)?
+        .into_par_iter()
+        .map(|data| {
+            let statistics = StatisticsFilter {
+                carrier: filter.carrier.clone(),
+                airport: Some(Identifier::Id(data.id)),
+                year: filter.year,
+                month: filter.month,
+                from: filter.from,
+                to: filter.to,
+            };
+
+            HalResource::new(&data)
+                .with_link("airport", format!("/airports/{}", data.id))
+                .with_link("statistics", format!("/statistics?{}", statistics))
+        })
+        .collect();
+
+    let result = HalResource::new(())
+        .with_link("self", format!("/rankings/airports?{}", query))
+        .with_resources("rankings", rankings);
+
+    Ok(Ok(Json(result)))
+}
+
+/// Get the default representation of the airports ranked by their
+/// performance. This is executed if the other routes are not matched.
+#[get("/airports?<by>&<order>&<min_flights>&<filter..>", rank = 4)]
+pub fn get_rankings_airports_default(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    by: Option<Result<Measure, ParameterError>>,
+    order: Option<Result<Order, ParameterError>>,
+    min_flights: Option<Result<i64, &RawStr>>,
+) -> Result<Result<Json<Vec<Ranking>>, ParameterError>, diesel::result::Error> {
+    get_rankings_airports_json(conn, filter, by, order, min_flights)
+}
@@ -0,0 +1,25 @@
+//! A module dealing with the "/rankings" routes.
+
+mod get_rankings_airports;
+mod get_rankings_carriers;
+mod ranking;
+mod views;
+
+use rocket::routes;
+
+/// Mount the rankings routes to the provided and `rocket` and return the resulting `rocket`.
+pub fn mount(rocket: rocket::Rocket) -> rocket::Rocket {
+    rocket.mount(
+        "/rankings",
+        routes!(
+            get_rankings_carriers::get_rankings_carriers_json,
+            get_rankings_carriers::get_rankings_carriers_csv,
+            get_rankings_carriers::get_rankings_carriers_hal,
+            get_rankings_carriers::get_rankings_carriers_default,
+            get_rankings_airports::get_rankings_airports_json,
+            get_rankings_airports::get_rankings_airports_csv,
+            get_rankings_airports::get_rankings_airports_hal,
+            get_rankings_airports::get_rankings_airports_default,
+        ),
+    )
+}
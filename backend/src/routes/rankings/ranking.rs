@@ -0,0 +1,265 @@
+//! A module providing the measures by which carriers and airports are ranked,
+//! and the ranking itself.
+
+use super::views::Ranking;
+
+use crate::routes::parameters::ParameterError;
+use crate::routes::statistics::filter::{filtered_join, sum_int8, GroupBy, StatisticsFilter};
+use diesel::{prelude::*, result::Error};
+use rocket::{http::RawStr, request::FromFormValue};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A measure of the performance of a carrier or an airport.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Measure {
+    /// The ratio of the flights on time to the total flights.
+    OnTimeRatio,
+
+    /// The ratio of the flights cancelled to the total flights.
+    CancellationRate,
+
+    /// The ratio of the flights diverted to the total flights.
+    DiversionRate,
+
+    /// The minutes delayed per flight.
+    DelayMinutesPerFlight,
+}
+
+impl Measure {
+    /// Every measure.
+    const ALL: &'static [Measure] = &[
+        Measure::OnTimeRatio,
+        Measure::CancellationRate,
+        Measure::DiversionRate,
+        Measure::DelayMinutesPerFlight,
+    ];
+
+    /// The name of the measure.
+    pub fn name(self) -> &'static str {
+        match self {
+            Measure::OnTimeRatio => "on_time_ratio",
+            Measure::CancellationRate => "cancellation_rate",
+            Measure::DiversionRate => "diversion_rate",
+            Measure::DelayMinutesPerFlight => "delay_minutes_per_flight",
+        }
+    }
+
+    /// Whether a higher value of the measure is a better performance.
+    fn higher_is_better(self) -> bool {
+        match self {
+            Measure::OnTimeRatio => true,
+            _ => false,
+        }
+    }
+
+    /// The value of the measure for the `ranking`.
+    fn value(self, ranking: &Ranking) -> Option<f64> {
+        match self {
+            Measure::OnTimeRatio => ranking.on_time_ratio,
+            Measure::CancellationRate => ranking.cancellation_rate,
+            Measure::DiversionRate => ranking.diversion_rate,
+            Measure::DelayMinutesPerFlight => ranking.delay_minutes_per_flight,
+        }
+    }
+}
+
+impl Default for Measure {
+    /// The on-time ratio.
+    fn default() -> Self {
+        Measure::OnTimeRatio
+    }
+}
+
+impl<'v> FromFormValue<'v> for Measure {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let value = form_value
+            .url_decode()
+            .map_err(|_| ParameterError::new("by", "not valid UTF-8"))?;
+
+        Measure::ALL
+            .iter()
+            .cloned()
+            .find(|measure| measure.name() == value.trim())
+            .ok_or_else(|| {
+                ParameterError::new(
+                    "by",
+                    format!(
+                        "unknown measure `{}`, expected one of on_time_ratio, cancellation_rate, \
+                         diversion_rate or delay_minutes_per_flight",
+                        value
+                    ),
+                )
+            })
+    }
+}
+
+impl fmt::Display for Measure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The end of the ranking which comes first, as given by the `order`
+/// parameter, either `best` or `worst`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Order {
+    /// The best performance comes first.
+    Best,
+
+    /// The worst performance comes first.
+    Worst,
+}
+
+impl Default for Order {
+    /// The best performance first.
+    fn default() -> Self {
+        Order::Best
+    }
+}
+
+impl<'v> FromFormValue<'v> for Order {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "best" => Ok(Order::Best),
+            "worst" => Ok(Order::Worst),
+            _ => Err(ParameterError::new(
+                "order",
+                format!("unknown order `{}`, expected best or worst", form_value),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Order::Best => write!(f, "best"),
+            Order::Worst => write!(f, "worst"),
+        }
+    }
+}
+
+/// The parameters of a ranking, taken from the query parameters of a request.
+pub struct RankingParameters {
+    /// The measure to rank by.
+    pub by: Measure,
+
+    /// The end of the ranking which comes first.
+    pub order: Order,
+
+    /// The minimum number of flights for a carrier or airport to be ranked.
+    pub min_flights: Option<i64>,
+}
+
+impl RankingParameters {
+    /// Check the `by`, `order` and `min_flights` parameters of a request, the
+    /// minimum number of flights must not be negative.
+    pub fn check(
+        by: Option<Result<Measure, ParameterError>>,
+        order: Option<Result<Order, ParameterError>>,
+        min_flights: Option<Result<i64, &RawStr>>,
+    ) -> Result<Self, ParameterError> {
+        let min_flights = match min_flights {
+            None => None,
+            Some(Ok(min_flights)) if min_flights >= 0 => Some(min_flights),
+            Some(_) => {
+                return Err(ParameterError::new(
+                    "min_flights",
+                    "the minimum number of flights must be a number of at least 0",
+                ))
+            }
+        };
+
+        Ok(RankingParameters {
+            by: by.transpose()?.unwrap_or_default(),
+            order: order.transpose()?.unwrap_or_default(),
+            min_flights,
+        })
+    }
+
+    /// Format the parameters as the query parameters of a URI.
+    pub fn to_query(&self) -> String {
+        let mut parameters = vec![format!("by={}", self.by), format!("order={}", self.order)];
+        if let Some(min_flights) = self.min_flights {
+            parameters.push(format!("min_flights={}", min_flights));
+        }
+        parameters.join("&")
+    }
+}
+
+/// Rank the carriers or airports of the `subject` by their performance in the
+/// statistics matching the `filter`. The totals of the statistics are summed by
+/// the database, those with fewer flights than the minimum are left out of the
+/// ranking. Carriers and airports without any flights can not be measured and
+/// come last. Equal performances share a rank.
+pub fn load_rankings(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    subject: GroupBy,
+    parameters: &RankingParameters,
+) -> Result<Vec<Ranking>, Error> {
+    let query = match filtered_join(conn, filter)? {
+        Some(query) => query,
+        None => return Ok(Vec::new()),
+    };
+
+    let (id, code, name) = subject.identity();
+    let rows = query
+        .select((
+            id,
+            code,
+            name,
+            sum_int8("flights_total"),
+            sum_int8("flights_on_time"),
+            sum_int8("flights_cancelled"),
+            sum_int8("flights_diverted"),
+            sum_int8("minutes_delayed_total"),
+        ))
+        .group_by(subject.grouping())
+        .load::<(i64, String, String, i64, i64, i64, i64, i64)>(conn)?;
+
+    let mut rankings: Vec<Ranking> = rows
+        .into_iter()
+        .filter(|(_, _, _, flights_total, _, _, _, _)| {
+            parameters
+                .min_flights
+                .map_or(true, |min_flights| *flights_total >= min_flights)
+        })
+        .map(Ranking::from)
+        .collect();
+
+    let measure = parameters.by;
+    let descending = measure.higher_is_better() == (parameters.order == Order::Best);
+    let compare = |a: Option<f64>, b: Option<f64>| match (a, b) {
+        (Some(a), Some(b)) if descending => b.partial_cmp(&a).unwrap_or(Ordering::Equal),
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    };
+    rankings.sort_by(|a, b| {
+        compare(measure.value(a), measure.value(b))
+            .then_with(|| a.code.cmp(&b.code))
+            .then_with(|| a.id.cmp(&b.id))
+    });
+
+    for index in 0..rankings.len() {
+        rankings[index].rank = if index > 0
+            && compare(
+                measure.value(&rankings[index - 1]),
+                measure.value(&rankings[index]),
+            ) == Ordering::Equal
+        {
+            rankings[index - 1].rank
+        } else {
+            index + 1
+        };
+    }
+
+    Ok(rankings)
+}
@@ -0,0 +1,92 @@
+//! A module containing the ranking structures returned by the API.
+
+use serde::Serialize;
+
+/// The performance of a carrier or an airport and its rank among the others,
+/// as returned by the API.
+#[derive(Serialize)]
+pub struct Ranking {
+    /// The rank, starting at 1. Equal performances share a rank.
+    pub rank: usize,
+
+    /// The id of the carrier or airport.
+    pub id: i64,
+
+    /// The code of the carrier or airport.
+    pub code: String,
+
+    /// The name of the carrier or airport.
+    pub name: String,
+
+    /// The total number of flights.
+    pub flights_total: i64,
+
+    /// The number of flights on time.
+    pub flights_on_time: i64,
+
+    /// The number of flights cancelled.
+    pub flights_cancelled: i64,
+
+    /// The number of flights diverted.
+    pub flights_diverted: i64,
+
+    /// The total number of minutes delayed.
+    pub minutes_delayed_total: i64,
+
+    /// The ratio of the flights on time to the total flights, if there are any
+    /// flights.
+    pub on_time_ratio: Option<f64>,
+
+    /// The ratio of the flights cancelled to the total flights, if there are
+    /// any flights.
+    pub cancellation_rate: Option<f64>,
+
+    /// The ratio of the flights diverted to the total flights, if there are any
+    /// flights.
+    pub diversion_rate: Option<f64>,
+
+    /// The minutes delayed per flight, if there are any flights.
+    pub delay_minutes_per_flight: Option<f64>,
+}
+
+impl From<(i64, String, String, i64, i64, i64, i64, i64)> for Ranking {
+    /// Measure the performance from the id, code and name of the carrier or
+    /// airport followed by its total, on time, cancelled and diverted flights
+    /// and its minutes delayed. The ranking is left to the caller.
+    fn from(
+        (
+            id,
+            code,
+            name,
+            flights_total,
+            flights_on_time,
+            flights_cancelled,
+            flights_diverted,
+            minutes_delayed_total,
+        ): (i64, String, String, i64, i64, i64, i64, i64),
+    ) -> Self {
+        let per_flight = |value: i64| {
+            if flights_total > 0 {
+                Some(value as f64 / flights_total as f64)
+            } else {
+                None
+            }
+        };
+
+        Self {
+            rank: 0,
+            id,
+            code,
+            name,
+            flights_total,
+            flights_on_time,
+            flights_cancelled,
+            flights_diverted,
+            minutes_delayed_total,
+            on_time_ratio: per_flight(flights_on_time),
+            cancellation_rate: per_flight(flights_cancelled),
+            diversion_rate: per_flight(flights_diverted),
+            delay_minutes_per_flight: per_flight(minutes_delayed_total),
+        }
+    }
+}
@@ -0,0 +1,459 @@
+//! A module providing the grouping and aggregation of the statistics, executed
+//! by the database.
+
+use super::columns::{column_names, Column};
+use super::filter::StatisticsQuery;
+use super::views::{AggregateStatistics, AggregateValue};
+
+use crate::database::schema;
+use crate::routes::parameters::ParameterError;
+use diesel::{
+    dsl::sql,
+    prelude::*,
+    result::Error,
+    sql_types::{Array, BigInt, Double, Integer, Nullable, Text},
+};
+use rocket::{http::RawStr, request::FromFormValue};
+use std::fmt;
+
+/// A dimension by which the statistics may be grouped.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dimension {
+    /// Group by the carrier.
+    Carrier,
+
+    /// Group by the airport.
+    Airport,
+
+    /// Group by the year.
+    Year,
+
+    /// Group by the month of the year, in any year.
+    Month,
+}
+
+impl Dimension {
+    /// Every dimension.
+    const ALL: &'static [Dimension] = &[
+        Dimension::Carrier,
+        Dimension::Airport,
+        Dimension::Year,
+        Dimension::Month,
+    ];
+
+    /// The name of the dimension.
+    pub fn name(self) -> &'static str {
+        match self {
+            Dimension::Carrier => "carrier",
+            Dimension::Airport => "airport",
+            Dimension::Year => "year",
+            Dimension::Month => "month",
+        }
+    }
+
+    /// The SQL expressions the statistics are grouped by, for the dimension.
+    fn group_by(self) -> &'static [&'static str] {
+        match self {
+            Dimension::Carrier => &["carriers.id", "carriers.code", "carriers.name"],
+            Dimension::Airport => &["airports.id", "airports.code", "airports.name"],
+            Dimension::Year => &["CAST(EXTRACT(YEAR FROM statistics.time) AS INT4)"],
+            Dimension::Month => &["CAST(EXTRACT(MONTH FROM statistics.time) AS INT4)"],
+        }
+    }
+
+    /// The SQL expressions the groups are ordered by, for the dimension.
+    fn order_by(self) -> &'static [&'static str] {
+        match self {
+            Dimension::Carrier => &["carriers.code", "carriers.id"],
+            Dimension::Airport => &["airports.code", "airports.id"],
+            Dimension::Year | Dimension::Month => self.group_by(),
+        }
+    }
+}
+
+/// The dimensions to group the statistics by as given by the `group_by`
+/// parameter, a comma separated list of `carrier`, `airport`, `year` and
+/// `month`, e.g. `group_by=carrier,year`. The groups are ordered by the
+/// dimensions in the order given.
+#[derive(Clone, Debug, Default)]
+pub struct GroupBy(Vec<Dimension>);
+
+impl<'v> FromFormValue<'v> for GroupBy {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let value = form_value
+            .url_decode()
+            .map_err(|_| ParameterError::new("group_by", "not valid UTF-8"))?;
+
+        let mut dimensions = Vec::new();
+        for name in value.split(',').map(str::trim) {
+            let dimension = Dimension::ALL
+                .iter()
+                .cloned()
+                .find(|dimension| dimension.name() == name)
+                .ok_or_else(|| {
+                    ParameterError::new(
+                        "group_by",
+                        format!(
+                            "unknown dimension `{}`, expected one of carrier, airport, year or \
+                             month",
+                            name
+                        ),
+                    )
+                })?;
+            if dimensions.contains(&dimension) {
+                return Err(ParameterError::new(
+                    "group_by",
+                    format!("dimension `{}` is given more than once", name),
+                ));
+            }
+            dimensions.push(dimension);
+        }
+
+        Ok(GroupBy(dimensions))
+    }
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = self.0.iter().map(|dimension| dimension.name()).collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+impl GroupBy {
+    /// Whether the statistics are grouped by the `dimension`.
+    pub fn contains(&self, dimension: Dimension) -> bool {
+        self.0.contains(&dimension)
+    }
+
+    /// Format the dimensions as a query parameter of a URI.
+    pub fn to_query(&self) -> String {
+        if self.0.is_empty() {
+            String::new()
+        } else {
+            format!("group_by={}", self)
+        }
+    }
+}
+
+/// An aggregate function applied to a column of the statistics.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Function {
+    /// The sum of the values.
+    Sum,
+
+    /// The mean of the values.
+    Avg,
+
+    /// The smallest of the values.
+    Min,
+
+    /// The largest of the values.
+    Max,
+}
+
+impl Function {
+    /// Every function.
+    const ALL: &'static [Function] = &[Function::Sum, Function::Avg, Function::Min, Function::Max];
+
+    /// The name of the function.
+    pub fn name(self) -> &'static str {
+        match self {
+            Function::Sum => "sum",
+            Function::Avg => "avg",
+            Function::Min => "min",
+            Function::Max => "max",
+        }
+    }
+}
+
+/// A metric computed for each group of statistics, an aggregate function
+/// applied to a column.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Metric {
+    /// The aggregate function.
+    pub function: Function,
+
+    /// The column aggregated.
+    pub column: Column,
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}({})", self.function.name(), self.column.name())
+    }
+}
+
+impl Metric {
+    /// Parse a metric of the form `function(column)`.
+    fn parse(name: &str) -> Result<Self, ParameterError> {
+        let invalid = || {
+            ParameterError::new(
+                "metrics",
+                format!(
+                    "invalid metric `{}`, expected a function of a column such as \
+                     `sum(flights_delayed)`",
+                    name
+                ),
+            )
+        };
+
+        let open = name.find('(').ok_or_else(invalid)?;
+        if !name.ends_with(')') {
+            return Err(invalid());
+        }
+        let function_name = name[..open].trim();
+        let column_name = name[open + 1..name.len() - 1].trim();
+
+        let function = Function::ALL
+            .iter()
+            .cloned()
+            .find(|function| function.name() == function_name.to_lowercase())
+            .ok_or_else(|| {
+                ParameterError::new(
+                    "metrics",
+                    format!(
+                        "unknown function `{}`, expected one of sum, avg, min or max",
+                        function_name
+                    ),
+                )
+            })?;
+        let column = Column::from_name(column_name).ok_or_else(|| {
+            ParameterError::new(
+                "metrics",
+                format!(
+                    "unknown column `{}`, expected one of {}",
+                    column_name,
+                    column_names()
+                ),
+            )
+        })?;
+        if column.is_aggregable() {
+            Ok(Metric { function, column })
+        } else {
+            Err(ParameterError::new(
+                "metrics",
+                format!("column `{}` can not be aggregated", column_name),
+            ))
+        }
+    }
+
+    /// The SQL expression of the metric. The value is a double precision
+    /// number so that every metric is of the same type.
+    fn sql(self) -> String {
+        format!(
+            "CAST({}(statistics.{}) AS FLOAT8)",
+            self.function.name().to_uppercase(),
+            self.column.name()
+        )
+    }
+
+    /// The value of the metric, as computed by the database. Only the mean is
+    /// not a whole number.
+    fn value(self, value: f64) -> AggregateValue {
+        match self.function {
+            Function::Avg => AggregateValue::Real(value),
+            _ => AggregateValue::Integer(value.round() as i64),
+        }
+    }
+}
+
+/// The metrics to compute for each group of statistics as given by the
+/// `metrics` parameter, a comma separated list of aggregate functions applied
+/// to columns, e.g. `metrics=sum(flights_delayed),avg(minutes_delayed_total)`.
+/// The metrics are represented in the order given.
+#[derive(Clone, Debug)]
+pub struct Metrics(Vec<Metric>);
+
+impl<'v> FromFormValue<'v> for Metrics {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let value = form_value
+            .url_decode()
+            .map_err(|_| ParameterError::new("metrics", "not valid UTF-8"))?;
+
+        let mut metrics = Vec::new();
+        for name in value.split(',').map(str::trim) {
+            let metric = Metric::parse(name)?;
+            if metrics.contains(&metric) {
+                return Err(ParameterError::new(
+                    "metrics",
+                    format!("metric `{}` is given more than once", metric),
+                ));
+            }
+            metrics.push(metric);
+        }
+
+        Ok(Metrics(metrics))
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<String> = self.0.iter().map(Metric::to_string).collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+impl Metrics {
+    /// Format the metrics as a query parameter of a URI.
+    pub fn to_query(&self) -> String {
+        format!("metrics={}", self)
+    }
+}
+
+/// The SQL expression of a key of the groups, which is null unless the
+/// statistics are grouped by the `dimension`.
+fn key(group_by: &GroupBy, dimension: Dimension, expression: &str) -> String {
+    if group_by.contains(dimension) {
+        String::from(expression)
+    } else {
+        String::from("NULL")
+    }
+}
+
+/// Group the statistics matched by the `query` by the dimensions of
+/// `group_by` and compute the `metrics` for each group. The statistics are
+/// joined to their carriers and airports so that the groups are described by
+/// them. Without any dimension the statistics form a single group.
+pub fn load_aggregate(
+    conn: &diesel::PgConnection,
+    query: StatisticsQuery,
+    group_by: &GroupBy,
+    metrics: &Metrics,
+) -> Result<Vec<AggregateStatistics>, Error> {
+    let metrics_sql = format!(
+        "ARRAY[{}]",
+        metrics
+            .0
+            .iter()
+            .map(|metric| metric.sql())
+            .collect::<Vec<String>>()
+            .join(", ")
+    );
+
+    let mut aggregate = schema::statistics::table
+        .inner_join(schema::carriers::table)
+        .inner_join(schema::airports::table)
+        .filter(schema::statistics::id.eq_any(query.select(schema::statistics::id)))
+        .select((
+            sql::<Nullable<BigInt>>(&key(group_by, Dimension::Carrier, "carriers.id")),
+            sql::<Nullable<Text>>(&key(group_by, Dimension::Carrier, "carriers.code")),
+            sql::<Nullable<Text>>(&key(group_by, Dimension::Carrier, "carriers.name")),
+            sql::<Nullable<BigInt>>(&key(group_by, Dimension::Airport, "airports.id")),
+            sql::<Nullable<Text>>(&key(group_by, Dimension::Airport, "airports.code")),
+            sql::<Nullable<Text>>(&key(group_by, Dimension::Airport, "airports.name")),
+            sql::<Nullable<Integer>>(&key(
+                group_by,
+                Dimension::Year,
+                Dimension::Year.group_by()[0],
+            )),
+            sql::<Nullable<Integer>>(&key(
+                group_by,
+                Dimension::Month,
+                Dimension::Month.group_by()[0],
+            )),
+            sql::<Array<Nullable<Double>>>(&metrics_sql),
+        ))
+        .into_boxed();
+
+    if !group_by.0.is_empty() {
+        let expressions = |clause: fn(Dimension) -> &'static [&'static str]| {
+            group_by
+                .0
+                .iter()
+                .flat_map(|dimension| clause(*dimension).iter().cloned())
+                .collect::<Vec<&str>>()
+                .join(", ")
+        };
+        aggregate = aggregate
+            .group_by(sql::<BigInt>(&expressions(Dimension::group_by)))
+            .order(sql::<BigInt>(&expressions(Dimension::order_by)));
+    }
+
+    let rows = aggregate.load::<(
+        Option<i64>,
+        Option<String>,
+        Option<String>,
+        Option<i64>,
+        Option<String>,
+        Option<String>,
+        Option<i32>,
+        Option<i32>,
+        Vec<Option<f64>>,
+    )>(conn)?;
+
+    Ok(rows
+        .into_iter()
+        .map(
+            |(
+                carrier_id,
+                carrier_code,
+                carrier_name,
+                airport_id,
+                airport_code,
+                airport_name,
+                year,
+                month,
+                values,
+            )| {
+                let mut fields = Vec::new();
+                for dimension in &group_by.0 {
+                    match dimension {
+                        Dimension::Carrier => {
+                            fields.push((
+                                String::from("carrier_id"),
+                                carrier_id.map(AggregateValue::Integer),
+                            ));
+                            fields.push((
+                                String::from("carrier_code"),
+                                carrier_code.clone().map(AggregateValue::Text),
+                            ));
+                            fields.push((
+                                String::from("carrier_name"),
+                                carrier_name.clone().map(AggregateValue::Text),
+                            ));
+                        }
+                        Dimension::Airport => {
+                            fields.push((
+                                String::from("airport_id"),
+                                airport_id.map(AggregateValue::Integer),
+                            ));
+                            fields.push((
+                                String::from("airport_code"),
+                                airport_code.clone().map(AggregateValue::Text),
+                            ));
+                            fields.push((
+                                String::from("airport_name"),
+                                airport_name.clone().map(AggregateValue::Text),
+                            ));
+                        }
+                        Dimension::Year => fields.push((
+                            String::from("year"),
+                            year.map(|year| AggregateValue::Integer(i64::from(year))),
+                        )),
+                        Dimension::Month => fields.push((
+                            String::from("month"),
+                            month.map(|month| AggregateValue::Integer(i64::from(month))),
+                        )),
+                    }
+                }
+
+                fields.extend(metrics.0.iter().zip(values).map(|(metric, value)| {
+                    (metric.to_string(), value.map(|value| metric.value(value)))
+                }));
+
+                AggregateStatistics {
+                    carrier_id,
+                    airport_id,
+                    year,
+                    month,
+                    fields,
+                }
+            },
+        )
+        .collect())
+}
@@ -0,0 +1,154 @@
+//! A module defining the columns of the statistics table that may be named in
+//! the query parameters of the statistics routes.
+
+use super::filter::StatisticsQuery;
+use super::views::{FieldValue, SparseStatistics};
+
+use crate::database::schema::statistics;
+use chrono::NaiveDate;
+use diesel::{
+    dsl::sql,
+    expression::BoxableExpression,
+    pg::Pg,
+    prelude::*,
+    result::Error,
+    sql_types::{BigInt, Date, Nullable},
+};
+
+/// Define the columns of the statistics table, along with their SQL and Rust
+/// types.
+macro_rules! columns {
+    ($($variant:ident => $column:ident: $sql_type:ident / $rust_type:ty),* $(,)*) => {
+        /// A column of the statistics table.
+        #[derive(Clone, Copy, Debug, PartialEq)]
+        pub enum Column {
+            $($variant,)*
+        }
+
+        impl Column {
+            /// Every column, in the order of the table.
+            pub const ALL: &'static [Column] = &[$(Column::$variant),*];
+
+            /// Find the column with the `name`.
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $(stringify!($column) => Some(Column::$variant),)*
+                    _ => None,
+                }
+            }
+
+            /// The name of the column.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Column::$variant => stringify!($column),)*
+                }
+            }
+
+            /// Order the `query` by the column, after any existing ordering.
+            pub fn order(self, query: StatisticsQuery, descending: bool) -> StatisticsQuery {
+                match (self, descending) {
+                    $(
+                        (Column::$variant, false) => {
+                            query.then_order_by(statistics::$column.asc())
+                        }
+                        (Column::$variant, true) => {
+                            query.then_order_by(statistics::$column.desc())
+                        }
+                    )*
+                }
+            }
+        }
+
+        /// Load the statistics of the `query`, selecting only the `columns`
+        /// from the database besides the id and time of each set of statistics.
+        /// The columns are represented in the order given.
+        pub fn load_columns(
+            conn: &diesel::PgConnection,
+            query: StatisticsQuery,
+            columns: &[Column],
+        ) -> Result<Vec<SparseStatistics>, Error> {
+            let rows = query
+                .select((
+                    statistics::id,
+                    statistics::time,
+                    ($(
+                        if columns.contains(&Column::$variant) {
+                            Box::new(statistics::$column.nullable())
+                                as Box<
+                                    dyn BoxableExpression<
+                                        statistics::table,
+                                        Pg,
+                                        SqlType = Nullable<$sql_type>,
+                                    >,
+                                >
+                        } else {
+                            Box::new(sql::<Nullable<$sql_type>>("NULL"))
+                        },
+                    )*),
+                ))
+                .load::<(i64, NaiveDate, ($(Option<$rust_type>,)*))>(conn)?;
+
+            Ok(rows
+                .into_iter()
+                .map(|(row_id, row_time, ($($column,)*))| SparseStatistics {
+                    id: row_id,
+                    time: row_time,
+                    fields: columns
+                        .iter()
+                        .map(|column| match column {
+                            $(
+                                Column::$variant => {
+                                    (column.name(), $column.map(FieldValue::from))
+                                }
+                            )*
+                        })
+                        .collect(),
+                })
+                .collect())
+        }
+    };
+}
+
+columns! {
+    Id => id: BigInt / i64,
+    FlightsCancelled => flights_cancelled: BigInt / i64,
+    FlightsDelayed => flights_delayed: BigInt / i64,
+    FlightsDiverted => flights_diverted: BigInt / i64,
+    FlightsOnTime => flights_on_time: BigInt / i64,
+    FlightsTotal => flights_total: BigInt / i64,
+    MinutesDelayedCarrier => minutes_delayed_carrier: BigInt / i64,
+    MinutesDelayedLateAircraft => minutes_delayed_late_aircraft: BigInt / i64,
+    MinutesDelayedNationalAviationSystem => minutes_delayed_national_aviation_system: BigInt / i64,
+    MinutesDelayedSecurity => minutes_delayed_security: BigInt / i64,
+    MinutesDelayedWeather => minutes_delayed_weather: BigInt / i64,
+    MinutesDelayedTotal => minutes_delayed_total: BigInt / i64,
+    NumberOfDelaysCarrier => number_of_delays_carrier: BigInt / i64,
+    NumberOfDelaysLateAircraft => number_of_delays_late_aircraft: BigInt / i64,
+    NumberOfDelaysNationalAviationSystem => number_of_delays_national_aviation_system: BigInt / i64,
+    NumberOfDelaysSecurity => number_of_delays_security: BigInt / i64,
+    NumberOfDelaysWeather => number_of_delays_weather: BigInt / i64,
+    Time => time: Date / NaiveDate,
+    CarrierId => carrier_id: BigInt / i64,
+    AirportId => airport_id: BigInt / i64,
+}
+
+impl Column {
+    /// Whether the values of the column are counts or minutes, which may be
+    /// summed and averaged, rather than an identifier or a date.
+    pub fn is_aggregable(self) -> bool {
+        match self {
+            Column::Id | Column::Time | Column::CarrierId | Column::AirportId => false,
+            _ => true,
+        }
+    }
+}
+
+/// The names of the columns, as listed in the description of an invalid query
+/// parameter.
+pub fn column_names() -> String {
+    Column::ALL
+        .iter()
+        .map(|column| column.name())
+        .collect::<Vec<&str>>()
+        .join(", ")
+}
@@ -0,0 +1,66 @@
+//! A module providing the sparse fieldsets of the statistics collections.
+
+use super::columns::{column_names, Column};
+
+use crate::routes::parameters::ParameterError;
+use rocket::{http::RawStr, request::FromFormValue};
+use std::fmt;
+
+/// The fields of the statistics to represent as given by the `fields`
+/// parameter, a comma separated list of columns, e.g.
+/// `fields=flights_on_time,minutes_delayed_weather,time`. The fields are
+/// represented in the order given.
+#[derive(Clone, Debug)]
+pub struct Fields(Vec<Column>);
+
+impl<'v> FromFormValue<'v> for Fields {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let value = form_value
+            .url_decode()
+            .map_err(|_| ParameterError::new("fields", "not valid UTF-8"))?;
+
+        let mut columns = Vec::new();
+        for name in value.split(',').map(str::trim) {
+            let column = Column::from_name(name).ok_or_else(|| {
+                ParameterError::new(
+                    "fields",
+                    format!(
+                        "unknown field `{}`, expected one of {}",
+                        name,
+                        column_names()
+                    ),
+                )
+            })?;
+            if columns.contains(&column) {
+                return Err(ParameterError::new(
+                    "fields",
+                    format!("field `{}` is given more than once", name),
+                ));
+            }
+            columns.push(column);
+        }
+
+        Ok(Fields(columns))
+    }
+}
+
+impl fmt::Display for Fields {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = self.0.iter().map(|column| column.name()).collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+impl Fields {
+    /// The columns of the fields, in the order given.
+    pub fn columns(&self) -> &[Column] {
+        &self.0
+    }
+
+    /// Format the fields as a query parameter of a URI.
+    pub fn to_query(&self) -> String {
+        format!("fields={}", self)
+    }
+}
@@ -0,0 +1,470 @@
+//! A module providing the filtration shared by the statistics routes.
+
+use super::sort::Sort;
+use super::views::SparseStatistics;
+
+use crate::database::{models, schema};
+use crate::routes::pagination::{Cursor, Page, PageRequest, Pagination, Positioned};
+use crate::routes::parameters::{Identifier, ParameterError, YearMonth};
+use chrono::NaiveDate;
+use diesel::{
+    dsl::*,
+    expression::SqlLiteral,
+    pg::Pg,
+    prelude::*,
+    result::Error,
+    sql_types::{BigInt, Bool, Integer, Text},
+};
+use rocket::{http::RawStr, request::FromFormValue, FromForm};
+use std::fmt;
+
+/// A query on the statistics table.
+pub type StatisticsQuery = schema::statistics::BoxedQuery<'static, Pg>;
+
+/// A query on the statistics matching a filter, joined with their carriers and
+/// airports.
+pub type StatisticsJoinQuery = IntoBoxed<
+    'static,
+    Filter<
+        InnerJoin<
+            InnerJoin<schema::statistics::table, schema::carriers::table>,
+            schema::airports::table,
+        >,
+        EqAny<schema::statistics::id, Select<StatisticsQuery, schema::statistics::id>>,
+    >,
+    Pg,
+>;
+
+/// Build a query on the statistics matching the `filter`, joined with their
+/// carriers and airports so that they can be grouped by either. `None` is
+/// returned if the filter can not match any statistics.
+pub fn filtered_join(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+) -> Result<Option<StatisticsJoinQuery>, Error> {
+    Ok(filter.query(conn)?.map(|query| {
+        schema::statistics::table
+            .inner_join(schema::carriers::table)
+            .inner_join(schema::airports::table)
+            .filter(schema::statistics::id.eq_any(query.select(schema::statistics::id)))
+            .into_boxed()
+    }))
+}
+
+/// The sum of the `column` of the statistics, as a `BIGINT` since the sum of
+/// an `INTEGER` column is a `NUMERIC`.
+pub fn sum_int8(column: &str) -> SqlLiteral<BigInt> {
+    sql::<BigInt>(&format!("CAST(SUM(statistics.{}) AS INT8)", column))
+}
+
+/// Whether the statistics are grouped by carrier or by airport, as given by a
+/// parameter such as `group_by`, either `carrier` or `airport`. This is not to
+/// be confused with the dimensions of the aggregation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GroupBy {
+    /// Group the statistics of each carrier.
+    Carrier,
+
+    /// Group the statistics at each airport.
+    Airport,
+}
+
+impl Default for GroupBy {
+    /// The carriers.
+    fn default() -> Self {
+        GroupBy::Carrier
+    }
+}
+
+impl<'v> FromFormValue<'v> for GroupBy {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "carrier" => Ok(GroupBy::Carrier),
+            "airport" => Ok(GroupBy::Airport),
+            _ => Err(ParameterError::new(
+                "group_by",
+                format!(
+                    "unknown group `{}`, expected carrier or airport",
+                    form_value
+                ),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for GroupBy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GroupBy::Carrier => write!(f, "carrier"),
+            GroupBy::Airport => write!(f, "airport"),
+        }
+    }
+}
+
+impl GroupBy {
+    /// The name of the table of the carriers or the airports.
+    pub fn table(self) -> &'static str {
+        match self {
+            GroupBy::Carrier => "carriers",
+            GroupBy::Airport => "airports",
+        }
+    }
+
+    /// The id, code and name of the carrier or airport of the statistics in a
+    /// joined query.
+    pub fn identity(self) -> (SqlLiteral<BigInt>, SqlLiteral<Text>, SqlLiteral<Text>) {
+        (
+            sql::<BigInt>(&format!("{}.id", self.table())),
+            sql::<Text>(&format!("{}.code", self.table())),
+            sql::<Text>(&format!("{}.name", self.table())),
+        )
+    }
+
+    /// The expression grouping a joined query by the identity of the carrier or
+    /// airport of the statistics.
+    pub fn grouping(self) -> SqlLiteral<BigInt> {
+        sql::<BigInt>(&format!("{0}.id, {0}.code, {0}.name", self.table()))
+    }
+}
+
+/// The filters as given in the query parameters of a request, before they are
+/// checked.
+#[derive(FromForm)]
+pub struct StatisticsFilterForm {
+    /// The `carrier` parameter.
+    carrier: Option<Result<Identifier, ParameterError>>,
+
+    /// The `airport` parameter.
+    airport: Option<Result<Identifier, ParameterError>>,
+
+    /// The `year` parameter.
+    year: Option<String>,
+
+    /// The `month` parameter.
+    month: Option<String>,
+
+    /// The `from` parameter.
+    from: Option<Result<YearMonth, ParameterError>>,
+
+    /// The `to` parameter.
+    to: Option<Result<YearMonth, ParameterError>>,
+}
+
+impl StatisticsFilterForm {
+    /// Check the filters of a request, the carrier and airport must be an id
+    /// or a code, the year a number, the month a number between 1 and 12, and
+    /// the period given by `from` and `to` must not end before it starts.
+    pub fn check(&self) -> Result<StatisticsFilter, ParameterError> {
+        let identifier = |identifier: &Option<Result<Identifier, ParameterError>>,
+                          parameter: &'static str| match identifier {
+            Some(Ok(identifier)) => Ok(Some(identifier.clone())),
+            Some(Err(error)) => Err(ParameterError::new(parameter, error.message.clone())),
+            None => Ok(None),
+        };
+        let year_month = |year_month: &Option<Result<YearMonth, ParameterError>>,
+                          parameter: &'static str| match year_month {
+            Some(Ok(year_month)) => Ok(Some(*year_month)),
+            Some(Err(error)) => Err(ParameterError::new(parameter, error.message.clone())),
+            None => Ok(None),
+        };
+
+        let carrier = identifier(&self.carrier, "carrier")?;
+        let airport = identifier(&self.airport, "airport")?;
+        let year = match &self.year {
+            Some(year) => match year.trim().parse::<i32>() {
+                Ok(year) => Some(year),
+                Err(_) => {
+                    return Err(ParameterError::new(
+                        "year",
+                        format!("`{}` is not a year", year),
+                    ))
+                }
+            },
+            None => None,
+        };
+        let month = match &self.month {
+            Some(month) => match month.trim().parse::<u32>() {
+                Ok(month) if month >= 1 && month <= 12 => Some(month),
+                _ => {
+                    return Err(ParameterError::new(
+                        "month",
+                        format!("`{}` is not a month between 1 and 12", month),
+                    ))
+                }
+            },
+            None => None,
+        };
+        let from = year_month(&self.from, "from")?;
+        let to = year_month(&self.to, "to")?;
+        if let (Some(from), Some(to)) = (from, to) {
+            if from > to {
+                return Err(ParameterError::new(
+                    "to",
+                    "the period must not end before it starts",
+                ));
+            }
+        }
+
+        Ok(StatisticsFilter {
+            carrier,
+            airport,
+            year,
+            month,
+            from,
+            to,
+        })
+    }
+}
+
+/// A filter resolved against the database.
+struct ResolvedFilter {
+    /// The ids of the carriers identified by the filter, if any.
+    carrier_ids: Option<Vec<i64>>,
+
+    /// The ids of the airports identified by the filter, if any.
+    airport_ids: Option<Vec<i64>>,
+
+    /// The first day of the year of the filter and of the following year, if
+    /// any.
+    year: Option<(NaiveDate, NaiveDate)>,
+}
+
+/// The filters which may be applied to the statistics, as checked from the
+/// query parameters of a request.
+pub struct StatisticsFilter {
+    /// Only include the statistics of the carrier(s) identified.
+    pub carrier: Option<Identifier>,
+
+    /// Only include the statistics of the airport identified.
+    pub airport: Option<Identifier>,
+
+    /// Only include the statistics of the year.
+    pub year: Option<i32>,
+
+    /// Only include the statistics of the month of the year, in any year.
+    pub month: Option<u32>,
+
+    /// Only include the statistics from this month onwards.
+    pub from: Option<YearMonth>,
+
+    /// Only include the statistics up to and including this month.
+    pub to: Option<YearMonth>,
+}
+
+impl StatisticsFilter {
+    /// Build a query on the statistics matching the filter. `None` is returned
+    /// if the filter can not match any statistics, for instance when the
+    /// carrier or airport does not identify anything.
+    pub fn query(&self, conn: &diesel::PgConnection) -> Result<Option<StatisticsQuery>, Error> {
+        Ok(self
+            .resolve(conn)?
+            .map(|resolved| self.resolved_query(&resolved)))
+    }
+
+    /// Resolve the filter against the database. `None` is returned if the
+    /// filter can not match any statistics.
+    fn resolve(&self, conn: &diesel::PgConnection) -> Result<Option<ResolvedFilter>, Error> {
+        let carrier_ids = match &self.carrier {
+            Some(carrier) => {
+                let carrier_ids = carrier.carrier_ids(conn)?;
+                if carrier_ids.is_empty() {
+                    return Ok(None);
+                }
+                Some(carrier_ids)
+            }
+            None => None,
+        };
+
+        let airport_ids = match &self.airport {
+            Some(airport) => {
+                let airport_ids = airport.airport_ids(conn)?;
+                if airport_ids.is_empty() {
+                    return Ok(None);
+                }
+                Some(airport_ids)
+            }
+            None => None,
+        };
+
+        let year = match self.year {
+            Some(year) => {
+                let first_day = NaiveDate::from_ymd_opt(year, 1, 1);
+                let next_first_day = year
+                    .checked_add(1)
+                    .and_then(|year| NaiveDate::from_ymd_opt(year, 1, 1));
+                match (first_day, next_first_day) {
+                    (Some(first_day), Some(next_first_day)) => Some((first_day, next_first_day)),
+                    _ => return Ok(None),
+                }
+            }
+            None => None,
+        };
+
+        Ok(Some(ResolvedFilter {
+            carrier_ids,
+            airport_ids,
+            year,
+        }))
+    }
+
+    /// Build a query on the statistics matching the filter, as `resolved`.
+    fn resolved_query(&self, resolved: &ResolvedFilter) -> StatisticsQuery {
+        let mut query = schema::statistics::table.into_boxed();
+
+        if let Some(carrier_ids) = &resolved.carrier_ids {
+            query = query.filter(schema::statistics::carrier_id.eq(any(carrier_ids.clone())));
+        }
+
+        if let Some(airport_ids) = &resolved.airport_ids {
+            query = query.filter(schema::statistics::airport_id.eq(any(airport_ids.clone())));
+        }
+
+        if let Some((first_day, next_first_day)) = resolved.year {
+            query = query.filter(
+                schema::statistics::time
+                    .ge(first_day)
+                    .and(schema::statistics::time.lt(next_first_day)),
+            );
+        }
+
+        if let Some(month) = self.month {
+            // Derived from the date in the database, so that the limit and
+            // offset apply to the filtered statistics.
+            query = query.filter(
+                sql::<Bool>("EXTRACT(MONTH FROM statistics.time) = ")
+                    .bind::<Integer, _>(month as i32),
+            );
+        }
+
+        if let Some(from) = self.from {
+            query = query.filter(schema::statistics::time.ge(from.first_day()));
+        }
+
+        if let Some(to) = self.to {
+            if let Some(next_first_day) = to.next_first_day() {
+                query = query.filter(schema::statistics::time.lt(next_first_day));
+            }
+        }
+
+        query
+    }
+
+    /// Load the `page` of the statistics matching the filter, in the order
+    /// given by `sort` or else in chronological order, along with the number
+    /// of statistics matching the filter. The page starts after its cursor if
+    /// there is one, otherwise at its offset. The statistics of the page are
+    /// loaded by `load`, from the query of the page.
+    pub fn load_page<T, F>(
+        &self,
+        conn: &diesel::PgConnection,
+        sort: Option<&Sort>,
+        page: PageRequest,
+        load: F,
+    ) -> Result<Page<T>, Error>
+    where
+        T: Positioned,
+        F: FnOnce(StatisticsQuery) -> Result<Vec<T>, Error>,
+    {
+        use crate::database::schema::statistics::{id, time};
+
+        let PageRequest {
+            limit,
+            offset,
+            cursor,
+        } = page;
+
+        // The filter is resolved once, and a query is built from it for the
+        // count and another for the page, as a boxed query can not be cloned.
+        let (total, mut items) = match self.resolve(conn)? {
+            None => (0, Vec::new()),
+            Some(resolved) => {
+                let total = self.resolved_query(&resolved).count().get_result(conn)?;
+                let query = match cursor {
+                    Some(cursor) => self.resolved_query(&resolved).filter(
+                        time.gt(cursor.time)
+                            .or(time.eq(cursor.time).and(id.gt(cursor.id))),
+                    ),
+                    None => self.resolved_query(&resolved).offset(offset),
+                };
+                let query = match sort {
+                    Some(sort) => sort.order(query),
+                    None => query.order((time, id)),
+                };
+                // One more than the limit is loaded to find out whether there
+                // is a next page.
+                (total, load(query.limit(limit.saturating_add(1)))?)
+            }
+        };
+
+        let chronological = sort.map_or(true, Sort::is_chronological);
+        let next_cursor = if items.len() as i64 > limit {
+            items.truncate(limit as usize);
+            items
+                .last()
+                .filter(|_| chronological)
+                .map(Positioned::cursor)
+        } else {
+            None
+        };
+
+        Ok(Page {
+            pagination: Pagination {
+                total,
+                limit,
+                offset: match cursor {
+                    Some(_) => None,
+                    None => Some(offset),
+                },
+                cursor,
+                next_cursor,
+            },
+            items,
+        })
+    }
+}
+
+impl Positioned for models::Statistics {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            time: self.time,
+            id: self.id,
+        }
+    }
+}
+
+impl Positioned for SparseStatistics {
+    fn cursor(&self) -> Cursor {
+        Cursor {
+            time: self.time,
+            id: self.id,
+        }
+    }
+}
+
+impl fmt::Display for StatisticsFilter {
+    /// Format the filter as the query parameters of a URI.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut parameters = Vec::new();
+        if let Some(carrier) = &self.carrier {
+            parameters.push(format!("carrier={}", carrier));
+        }
+        if let Some(airport) = &self.airport {
+            parameters.push(format!("airport={}", airport));
+        }
+        if let Some(year) = self.year {
+            parameters.push(format!("year={}", year));
+        }
+        if let Some(month) = self.month {
+            parameters.push(format!("month={}", month));
+        }
+        if let Some(from) = self.from {
+            parameters.push(format!("from={}", from));
+        }
+        if let Some(to) = self.to {
+            parameters.push(format!("to={}", to));
+        }
+        write!(f, "{}", parameters.join("&"))
+    }
+}
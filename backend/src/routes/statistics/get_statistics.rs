@@ -1,191 +1,179 @@
 //! A module dealing with the `get_statistics` routes.
 
-use super::views::Statistics;
-
+use super::columns::{load_columns, Column};
+use super::fields::Fields;
+use super::filter::{StatisticsFilter, StatisticsFilterForm};
+use super::sort::{check_sort, Sort};
+use super::views::SparseStatistics;
+
+use crate::routes::pagination::{Cursor, Page, PageRequest, PagedCsv};
+use crate::routes::parameters::{join_query, ParameterError};
 use crate::CorgisDbConn;
-use diesel::{dsl::*, prelude::*, result::Error};
+use diesel::result::Error;
 use rayon::prelude::*;
-use rocket::get;
+use rocket::{get, http::RawStr, request::LenientForm};
 use rocket_contrib::json::Json;
 use rocket_contrib_local::csv::Csv;
 use rustic_hal::HalResource;
 
-const LIMIT: usize = 100;
-const OFFSET: usize = 0;
-
 fn get_statistics_data(
     conn: &diesel::PgConnection,
-    carrier: Option<i64>,
-    airport: Option<i64>,
-    month: Option<u32>,
-    limit: Option<usize>,
-    offset: Option<usize>,
-) -> Result<Vec<Statistics>, Error> {
-    use crate::database::{models, schema};
-    use chrono::Datelike;
-
-    let offset = offset.unwrap_or(OFFSET) as i64;
-    let limit = limit.unwrap_or(LIMIT) as i64;
-
-    let statistics: Vec<models::Statistics> = match (carrier, airport) {
-        (None, None) => match schema::statistics::table
-            .limit(limit)
-            .offset(offset)
-            .load(conn)
-            .optional()?
-        {
-            Some(statistics_data) => statistics_data,
-            None => return Ok(Vec::new()),
-        },
-        (None, Some(airport)) => {
-            let airport_id: i64 = match schema::airports::table
-                .find(airport)
-                .select(schema::airports::id)
-                .first(conn)
-                .optional()?
-            {
-                None => return Ok(Vec::new()),
-                Some(airport_id) => airport_id,
-            };
-            match schema::statistics::table
-                .filter(schema::statistics::airport_id.eq(airport_id))
-                .limit(limit)
-                .offset(offset)
-                .load::<models::Statistics>(conn)
-                .optional()?
-            {
-                Some(data) => data,
-                None => Vec::new(),
-            }
-        }
-        (Some(carrier), None) => {
-            let carrier_id: i64 = match schema::carriers::table
-                .find(carrier)
-                .select(schema::carriers::id)
-                .first(conn)
-                .optional()?
-            {
-                None => return Ok(Vec::new()),
-                Some(carrier_id) => carrier_id,
-            };
-            match schema::statistics::table
-                .filter(schema::statistics::carrier_id.eq(carrier_id))
-                .limit(limit)
-                .offset(offset)
-                .load::<models::Statistics>(conn)
-                .optional()?
-            {
-                Some(data) => data,
-                None => Vec::new(),
-            }
-        }
-        (Some(carrier), Some(airport)) => {
-            let airport_id: i64 = match schema::airports::table
-                .find(airport)
-                .select(schema::airports::id)
-                .first(conn)
-                .optional()?
-            {
-                None => return Ok(Vec::new()),
-                Some(airport_id) => airport_id,
-            };
-
-            let carrier_id: i64 = match schema::carriers::table
-                .find(carrier)
-                .select(schema::carriers::id)
-                .first(conn)
-                .optional()?
-            {
-                None => return Ok(Vec::new()),
-                Some(carrier_id) => carrier_id,
-            };
-
-            match schema::statistics::table
-                .filter(
-                    schema::statistics::airport_id
-                        .eq(airport_id)
-                        .and(schema::statistics::carrier_id.eq(carrier_id)),
-                )
-                .limit(limit)
-                .offset(offset)
-                .load::<models::Statistics>(conn)
-                .optional()?
-            {
-                Some(data) => data,
-                None => Vec::new(),
-            }
-        }
-    };
-
-    let statistics = match month {
-        Some(month) => statistics
-            .into_par_iter()
-            .filter(|record| record.time.month() == month)
-            .collect(),
-        None => statistics,
-    };
+    filter: &StatisticsFilter,
+    sort: Option<&Sort>,
+    fields: Option<&Fields>,
+    page: PageRequest,
+) -> Result<Page<SparseStatistics>, Error> {
+    let columns = fields.map_or(Column::ALL, Fields::columns);
+
+    filter.load_page(conn, sort, page, |query| load_columns(conn, query, columns))
+}
 
-    Ok(statistics.into_par_iter().map(Statistics::from).collect())
+/// Check the `sort` and `fields` parameters of a request.
+fn check_parameters(
+    sort: Option<Result<Sort, ParameterError>>,
+    fields: Option<Result<Fields, ParameterError>>,
+    cursor: Option<Cursor>,
+) -> Result<(Option<Sort>, Option<Fields>), ParameterError> {
+    Ok((check_sort(sort, cursor)?, fields.transpose()?))
 }
 
-/// Get the JSON representation of the statistics in the database.
+/// Get the JSON representation of the statistics in the database, a page at a
+/// time. Only the `fields` are represented, if given.
 #[get(
-    "/?<carrier>&<airport>&<month>&<limit>&<offset>",
+    "/?<sort>&<fields>&<limit>&<offset>&<cursor>&<filter..>",
     format = "application/json",
     rank = 1
 )]
 pub fn get_statistics_json(
     conn: CorgisDbConn,
-    carrier: Option<i64>,
-    airport: Option<i64>,
-    month: Option<u32>,
-    limit: Option<usize>,
-    offset: Option<usize>,
-) -> Result<Json<Vec<Statistics>>, Error> {
-    get_statistics_data(&conn, carrier, airport, month, limit, offset).map(Json)
+    filter: LenientForm<StatisticsFilterForm>,
+    sort: Option<Result<Sort, ParameterError>>,
+    fields: Option<Result<Fields, ParameterError>>,
+    limit: Option<Result<usize, &RawStr>>,
+    offset: Option<Result<usize, &RawStr>>,
+    cursor: Option<Result<Cursor, ParameterError>>,
+) -> Result<Result<Json<Page<SparseStatistics>>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let page_request = match PageRequest::check(limit, offset, cursor) {
+        Ok(page_request) => page_request,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (sort, fields) = match check_parameters(sort, fields, page_request.cursor) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    get_statistics_data(&conn, &filter, sort.as_ref(), fields.as_ref(), page_request)
+        .map(|data| Ok(Json(data)))
 }
 
-/// Get the CSV representation of the statistics in the database.
+/// Get the CSV representation of the statistics in the database, a page at a
+/// time. Only the `fields` are represented, if given, in which case the columns
+/// are in the order of the fields. The pagination is described by the headers
+/// of the response.
 #[get(
-    "/?<carrier>&<airport>&<month>&<limit>&<offset>",
+    "/?<sort>&<fields>&<limit>&<offset>&<cursor>&<filter..>",
     format = "text/csv",
     rank = 2
 )]
 pub fn get_statistics_csv(
     conn: CorgisDbConn,
-    carrier: Option<i64>,
-    airport: Option<i64>,
-    month: Option<u32>,
-    limit: Option<usize>,
-    offset: Option<usize>,
-) -> Result<Csv<Vec<Statistics>>, Error> {
-    fn convertor(statistics_set: &Vec<Statistics>) -> String {
+    filter: LenientForm<StatisticsFilterForm>,
+    sort: Option<Result<Sort, ParameterError>>,
+    fields: Option<Result<Fields, ParameterError>>,
+    limit: Option<Result<usize, &RawStr>>,
+    offset: Option<Result<usize, &RawStr>>,
+    cursor: Option<Result<Cursor, ParameterError>>,
+) -> Result<Result<PagedCsv<Vec<SparseStatistics>>, ParameterError>, Error> {
+    fn convertor(statistics_set: &Vec<SparseStatistics>) -> String {
         let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        if let Some(statistics) = statistics_set.first() {
+            wtr.write_record(statistics.fields.iter().map(|(name, _)| name))
+                .unwrap();
+        }
         for statistics in statistics_set {
-            wtr.serialize(statistics).unwrap();
+            wtr.write_record(statistics.fields.iter().map(|(_, value)| match value {
+                Some(value) => value.to_string(),
+                None => String::new(),
+            }))
+            .unwrap();
         }
         String::from_utf8(wtr.into_inner().unwrap()).unwrap()
     };
 
-    get_statistics_data(&conn, carrier, airport, month, limit, offset)
-        .map(|data| Csv(data, convertor))
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let page_request = match PageRequest::check(limit, offset, cursor) {
+        Ok(page_request) => page_request,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (sort, fields) = match check_parameters(sort, fields, page_request.cursor) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![
+        filter.to_string(),
+        sort.as_ref().map_or_else(String::new, Sort::to_query),
+        fields.as_ref().map_or_else(String::new, Fields::to_query),
+    ]);
+    let page = get_statistics_data(&conn, &filter, sort.as_ref(), fields.as_ref(), page_request)?;
+
+    Ok(Ok(page.pagination.csv(
+        "/statistics",
+        &query,
+        Csv(page.items, convertor),
+    )))
 }
 
-/// Get the HAL representation of the statistics in the database.
+/// Get the HAL representation of the statistics in the database, a page at a
+/// time. Only the `fields` are represented, if given.
 #[get(
-    "/?<carrier>&<airport>&<month>&<limit>&<offset>",
+    "/?<sort>&<fields>&<limit>&<offset>&<cursor>&<filter..>",
     format = "application/hal+json",
     rank = 3
 )]
 pub fn get_statistics_hal(
     conn: CorgisDbConn,
-    carrier: Option<i64>,
-    airport: Option<i64>,
-    month: Option<u32>,
-    limit: Option<usize>,
-    offset: Option<usize>,
-) -> Result<Json<Vec<HalResource>>, Error> {
-    let result = get_statistics_data(&conn, carrier, airport, month, limit, offset)?
+    filter: LenientForm<StatisticsFilterForm>,
+    sort: Option<Result<Sort, ParameterError>>,
+    fields: Option<Result<Fields, ParameterError>>,
+    limit: Option<Result<usize, &RawStr>>,
+    offset: Option<Result<usize, &RawStr>>,
+    cursor: Option<Result<Cursor, ParameterError>>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let page_request = match PageRequest::check(limit, offset, cursor) {
+        Ok(page_request) => page_request,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (sort, fields) = match check_parameters(sort, fields, page_request.cursor) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![
+        filter.to_string(),
+        sort.as_ref().map_or_else(String::new, Sort::to_query),
+        fields.as_ref().map_or_else(String::new, Fields::to_query),
+    ]);
+    let page = get_statistics_data(&conn, &filter, sort.as_ref(), fields.as_ref(), page_request)?;
+    let statistics = page
+        .items
         .into_par_iter()
         .map(|data| {
             HalResource::new(&data)
@@ -196,19 +184,24 @@ pub fn get_statistics_hal(
         })
         .collect();
 
-    Ok(Json(result))
+    let result = page
+        .pagination
+        .hal("/statistics", &query, "statistics", statistics);
+
+    Ok(Ok(Json(result)))
 }
 
 /// Get the default representation of the statistics in the data store. This is
 /// executed if the other routes are not matched.
-#[get("/?<carrier>&<airport>&<month>&<limit>&<offset>", rank = 4)]
+#[get("/?<sort>&<fields>&<limit>&<offset>&<cursor>&<filter..>", rank = 4)]
 pub fn get_statistics_default(
     conn: CorgisDbConn,
-    carrier: Option<i64>,
-    airport: Option<i64>,
-    month: Option<u32>,
-    limit: Option<usize>,
-    offset: Option<usize>,
-) -> Result<Json<Vec<Statistics>>, diesel::result::Error> {
-    get_statistics_json(conn, carrier, airport, month, limit, offset)
+    filter: LenientForm<StatisticsFilterForm>,
+    sort: Option<Result<Sort, ParameterError>>,
+    fields: Option<Result<Fields, ParameterError>>,
+    limit: Option<Result<usize, &RawStr>>,
+    offset: Option<Result<usize, &RawStr>>,
+    cursor: Option<Result<Cursor, ParameterError>>,
+) -> Result<Result<Json<Page<SparseStatistics>>, ParameterError>, diesel::result::Error> {
+    get_statistics_json(conn, filter, sort, fields, limit, offset, cursor)
 }
@@ -0,0 +1,194 @@
+//! A module dealing with the `get_statistics_aggregate` routes.
+
+use super::aggregate::{load_aggregate, GroupBy, Metrics};
+use super::filter::{StatisticsFilter, StatisticsFilterForm};
+use super::views::AggregateStatistics;
+
+use crate::routes::parameters::{join_query, Identifier, ParameterError};
+use crate::CorgisDbConn;
+use diesel::result::Error;
+use rayon::prelude::*;
+use rocket::{get, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+
+fn get_statistics_aggregate_data(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    group_by: &GroupBy,
+    metrics: &Metrics,
+) -> Result<Vec<AggregateStatistics>, Error> {
+    match filter.query(conn)? {
+        Some(query) => load_aggregate(conn, query, group_by, metrics),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Check the `group_by` and `metrics` parameters of a request, at least one
+/// metric is required.
+fn check_parameters(
+    group_by: Option<Result<GroupBy, ParameterError>>,
+    metrics: Option<Result<Metrics, ParameterError>>,
+) -> Result<(GroupBy, Metrics), ParameterError> {
+    let group_by = group_by.transpose()?.unwrap_or_default();
+    match metrics {
+        Some(metrics) => Ok((group_by, metrics?)),
+        None => Err(ParameterError::new(
+            "metrics",
+            "at least one metric is required, e.g. `metrics=sum(flights_delayed)`",
+        )),
+    }
+}
+
+/// Get the JSON representation of the statistics grouped by the `group_by`
+/// dimensions, with the `metrics` of each group.
+#[get(
+    "/aggregate?<group_by>&<metrics>&<filter..>",
+    format = "application/json",
+    rank = 1
+)]
+pub fn get_statistics_aggregate_json(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    group_by: Option<Result<GroupBy, ParameterError>>,
+    metrics: Option<Result<Metrics, ParameterError>>,
+) -> Result<Result<Json<Vec<AggregateStatistics>>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (group_by, metrics) = match check_parameters(group_by, metrics) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    get_statistics_aggregate_data(&conn, &filter, &group_by, &metrics).map(|data| Ok(Json(data)))
+}
+
+/// Get the CSV representation of the statistics grouped by the `group_by`
+/// dimensions, with the `metrics` of each group. The keys of the groups come
+/// first, followed by the metrics in the order given.
+#[get(
+    "/aggregate?<group_by>&<metrics>&<filter..>",
+    format = "text/csv",
+    rank = 2
+)]
+pub fn get_statistics_aggregate_csv(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    group_by: Option<Result<GroupBy, ParameterError>>,
+    metrics: Option<Result<Metrics, ParameterError>>,
+) -> Result<Result<Csv<Vec<AggregateStatistics>>, ParameterError>, Error> {
+    fn convertor(groups: &Vec<AggregateStatistics>) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        if let Some(group) = groups.first() {
+            wtr.write_record(group.fields.iter().map(|(name, _)| name))
+                .unwrap();
+        }
+        for group in groups {
+            wtr.write_record(group.fields.iter().map(|(_, value)| match value {
+                Some(value) => value.to_string(),
+                None => String::new(),
+            }))
+            .unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (group_by, metrics) = match check_parameters(group_by, metrics) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    get_statistics_aggregate_data(&conn, &filter, &group_by, &metrics)
+        .map(|data| Ok(Csv(data, convertor)))
+}
+
+/// Get the HAL representation of the statistics grouped by the `group_by`
+/// dimensions, with the `metrics` of each group. Each group links to its
+/// carrier and airport, and to the statistics it aggregates.
+#[get(
+    "/aggregate?<group_by>&<metrics>&<filter..>",
+    format = "application/hal+json",
+    rank = 3
+)]
+pub fn get_statistics_aggregate_hal(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    group_by: Option<Result<GroupBy, ParameterError>>,
+    metrics: Option<Result<Metrics, ParameterError>>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (group_by, metrics) = match check_parameters(group_by, metrics) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![
+        filter.to_string(),
+        group_by.to_query(),
+        metrics.to_query(),
+    ]);
+    let groups = get_statistics_aggregate_data(&conn, &filter, &group_by, &metrics)?
+        .into_par_iter()
+        .map(|data| {
+            // The statistics of the group are those of the request narrowed
+            // down by the keys of the group.
+            let statistics = StatisticsFilter {
+                carrier: data
+                    .carrier_id
+                    .map(Identifier::Id)
+                    .or_else(|| filter.carrier.clone()),
+                airport: data
+                    .airport_id
+                    .map(Identifier::Id)
+                    .or_else(|| filter.airport.clone()),
+                year: data.year.or(filter.year),
+                month: data.month.map(|month| month as u32).or(filter.month),
+                from: filter.from,
+                to: filter.to,
+            };
+
+            let resource = HalResource::new(&data)
+                .with_link("statistics", format!("/statistics?{}", statistics));
+            let resource = match data.carrier_id {
+                Some(carrier) => resource.with_link("carrier", format!("/carriers/{}", carrier)),
+                None => resource,
+            };
+            match data.airport_id {
+                Some(airport) => resource.with_link("airport", format!("/airports/{}", airport)),
+                None => resource,
+            }
+        })
+        .collect();
+
+    let result = HalResource::new(())
+        .with_link("self", format!("/statistics/aggregate?{}", query))
+        .with_resources("groups", groups);
+
+    Ok(Ok(Json(result)))
+}
+
+/// Get the default representation of the statistics grouped by the `group_by`
+/// dimensions, with the `metrics` of each group. This is executed if the other
+/// routes are not matched.
+#[get("/aggregate?<group_by>&<metrics>&<filter..>", rank = 4)]
+pub fn get_statistics_aggregate_default(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    group_by: Option<Result<GroupBy, ParameterError>>,
+    metrics: Option<Result<Metrics, ParameterError>>,
+) -> Result<Result<Json<Vec<AggregateStatistics>>, ParameterError>, diesel::result::Error> {
+    get_statistics_aggregate_json(conn, filter, group_by, metrics)
+}
@@ -1,158 +1,211 @@
-// Request the data needed for the get_statistics_connection routes from the database.
-// fn get_statistics_connection_data(
-//     conn: &diesel::SqliteConnection,
-//     airport1: String,
-//     airport2: String,
-//     carrier: Option<String>,
-// ) -> Result<Vec<HashMap<String, f64>>, NotFound<String>> {
-//     use crate::database::{
-//         models::{Airport, Carrier},
-//         schema::{airports, carriers, statistics},
-//     };
-
-//     let airport1 = airports::table
-//         .filter(airports::code.eq(airport1))
-//         .first::<Airport>(conn)
-//         .expect("Error loading airport");
-
-//     let airport2 = airports::table
-//         .filter(airports::code.eq(airport2))
-//         .first::<Airport>(conn)
-//         .expect("Error loading airport");
-
-//     use diesel::dsl::avg;
-
-//     match carrier {
-//         Some(carrier) => {
-//             let carrier = carriers::table
-//                 .filter(carriers::code.eq(carrier))
-//                 .first::<Carrier>(conn)
-//                 .expect("Error loading carrier");
-
-//             let mean1_late_aircraft: Option<BigDecimal> = statistics::table
-//                 .filter(statistics::airport_id.eq(airport1.id))
-//                 .filter(statistics::carrier_id.eq(carrier.id))
-//                 .select(avg(statistics::minutes_delayed_late_aircraft))
-//                 .get_result(conn)
-//                 .unwrap();
-
-//             let mean1_delayed_carrier: BigDecimal = statistics::table
-//                 .filter(statistics::airport_id.eq(airport1.id))
-//                 .filter(statistics::carrier_id.eq(carrier.id))
-//                 .select(avg(statistics::minutes_delayed_carrier))
-//                 .get_result(conn)
-//                 .unwrap()
-//                 .unwrap();
-
-//             let mean2_late_aircraft: BigDecimal = statistics::table
-//                 .filter(statistics::airport_id.eq(airport2.id))
-//                 .filter(statistics::carrier_id.eq(carrier.id))
-//                 .select(avg(statistics::minutes_delayed_late_aircraft))
-//                 .get_result(conn)
-//                 .unwrap()
-//                 .unwrap();
-//             let mean2_delayed_carrier: BigDecimal = statistics::table
-//                 .filter(statistics::airport_id.eq(airport2.id))
-//                 .filter(statistics::carrier_id.eq(carrier.id))
-//                 .select(avg(statistics::minutes_delayed_carrier))
-//                 .get_result(conn)
-//                 .unwrap()
-//                 .unwrap();
-
-//             println!(
-//                 "{} {} {} {}",
-//                 mean1_late_aircraft,
-//                 mean2_late_aircraft,
-//                 mean1_delayed_carrier,
-//                 mean2_delayed_carrier
-//             );
-
-//             // let carrier_delays = DescriptiveStatistics {
-//             //     mean: (mean1_delayed_carrier + mean2_delayed_carrier) / 2.,
-//             // };
-//             // let late_aircraft_delays = DescriptiveStatistics {
-//             //     mean: (mean1_late_aircraft + mean2_late_aircraft) / 2.,
-//             // };
-
-//             // Ok(vec![ConnectionStatistics {
-//             //     carrier_delays,
-//             //     late_aircraft_delays,
-//             // }])
-//             unimplemented!();
-//         }
-//         None => {
-//             unimplemented!();
-//         }
-//     }
-// }
-
-// /// Get the JSON representation of the connections statistics between 2 airports.
-// #[get(
-//     "/connection?<airport1>&<airport2>&<carrier>",
-//     format = "application/json",
-//     rank = 1
-// )]
-// pub fn get_statistics_connection_json(
-//     conn: CorgisDbConn,
-//     airport1: String,
-//     airport2: String,
-//     carrier: Option<String>,
-// ) -> Result<Json<HashSet<String, f64>>, diesel::result::Error> {
-//     get_statistics_connection_data(&conn, airport1, airport2, carrier).map(Json)
-// }
-
-// /// Get the CSV representation of the connections statistics between 2 airports.
-// #[get(
-//     "/connection?<airport1>&<airport2>&<carrier>",
-//     format = "text/csv",
-//     rank = 2
-// )]
-// pub fn get_statistics_connection_csv(
-//     conn: CorgisDbConn,
-//     airport1: String,
-//     airport2: String,
-//     carrier: Option<String>,
-// ) -> Result<Csv<HashMap<String, f64>>, diesel::result::Error> {
-//     fn convertor(statistics: &Vec<ConnectionStatistics>) -> String {
-//         let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
-//         for statistic in statistics {
-//             wtr.serialize(statistic).unwrap();
-//         }
-//         String::from_utf8(wtr.into_inner().unwrap()).unwrap()
-//     };
-//     get_statistics_connection_data(&conn, airport1, airport2, carrier)
-//         .map(|data| Csv(data, convertor))
-// }
-
-// /// Get the HAL representation of the connections statistics between 2 airports.
-// #[get(
-//     "/connection?<airport1>&<airport2>&<carrier>",
-//     format = "application/hal+json",
-//     rank = 3
-// )]
-// pub fn get_statistics_connection_hal(
-//     conn: CorgisDbConn,
-//     airport1: String,
-//     airport2: String,
-//     carrier: Option<String>,
-// ) -> Result<Json<HalResource>, Error> {
-//     let data = get_statistics_data(&conn, statistics)?;
-//     let result = HalResource::new(&data)
-//         .with_link("self", format!("/statistics/{}", data.id))
-//         .with_link("delete", format!("/statistics/{}", data.id))
-//         .with_link("patch", format!("/statistics/{}", data.id))
-//         .with_link("put", format!("/statistics/{}", data.id));
-
-//     Ok(Json(result))
-// }
-
-// /// Get the default representation of the connections statistics between 2 airports. This is executed if the other routes are not matched.
-// #[get("/connection?<airport1>&<airport2>&<carrier>", rank = 4)]
-// pub fn get_statistics_connection_default(
-//     conn: CorgisDbConn,
-//     airport1: String,
-//     airport2: String,
-//     carrier: Option<String>,
-// ) -> Result<Json<HashMap<String, f64>>, diesel::result::Error> {
-//     get_statistics_connection_json(conn, airport1, airport2, carrier)
-// }
+//! A module dealing with the `get_statistics_connection` routes.
+
+use super::views::{ConnectionStatistics, DescriptiveStatistics};
+
+use crate::routes::parameters::{Identifier, ParameterError};
+use crate::CorgisDbConn;
+use diesel::{dsl::*, prelude::*, result::Error};
+use rocket::get;
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+use serde::Serialize;
+
+/// Check the `airport1`, `airport2` and `carrier` parameters of a request,
+/// each must be an id or a code.
+fn check_parameters(
+    airport1: Result<Identifier, ParameterError>,
+    airport2: Result<Identifier, ParameterError>,
+    carrier: Option<Result<Identifier, ParameterError>>,
+) -> Result<(Identifier, Identifier, Option<Identifier>), ParameterError> {
+    let rename =
+        |parameter| move |error: ParameterError| ParameterError::new(parameter, error.message);
+    Ok((
+        airport1.map_err(rename("airport1"))?,
+        airport2.map_err(rename("airport2"))?,
+        carrier.transpose().map_err(rename("carrier"))?,
+    ))
+}
+
+fn get_statistics_connection_data(
+    conn: &diesel::PgConnection,
+    airport1: &Identifier,
+    airport2: &Identifier,
+    carrier: Option<&Identifier>,
+) -> Result<Option<ConnectionStatistics>, Error> {
+    use crate::database::schema;
+
+    // Airport codes are unique, so each identifies at most one airport.
+    let airport1_id = match airport1.airport_ids(conn)?.first() {
+        None => return Ok(None),
+        Some(airport_id) => *airport_id,
+    };
+
+    let airport2_id = match airport2.airport_ids(conn)?.first() {
+        None => return Ok(None),
+        Some(airport_id) => *airport_id,
+    };
+
+    let query = schema::statistics::table
+        .filter(schema::statistics::airport_id.eq(any(vec![airport1_id, airport2_id])))
+        .select((
+            schema::statistics::minutes_delayed_carrier,
+            schema::statistics::minutes_delayed_late_aircraft,
+        ))
+        .into_boxed();
+
+    let (query, carriers) = match carrier {
+        None => (query, None),
+        Some(carrier) => {
+            let carrier_ids = carrier.carrier_ids(conn)?;
+            if carrier_ids.is_empty() {
+                return Ok(None);
+            }
+            (
+                query.filter(schema::statistics::carrier_id.eq(any(carrier_ids.clone()))),
+                Some(carrier_ids),
+            )
+        }
+    };
+
+    let (minutes_delayed_carrier, minutes_delayed_late_aircraft): (Vec<i64>, Vec<i64>) =
+        query.load::<(i64, i64)>(conn)?.into_iter().unzip();
+
+    Ok(Some(ConnectionStatistics {
+        airport1: airport1_id,
+        airport2: airport2_id,
+        carriers,
+        minutes_delayed_carrier: DescriptiveStatistics::from(minutes_delayed_carrier),
+        minutes_delayed_late_aircraft: DescriptiveStatistics::from(minutes_delayed_late_aircraft),
+    }))
+}
+
+/// Get the JSON representation of the connection statistics between 2 airports.
+#[get(
+    "/connection?<airport1>&<airport2>&<carrier>",
+    format = "application/json",
+    rank = 1
+)]
+pub fn get_statistics_connection_json(
+    conn: CorgisDbConn,
+    airport1: Result<Identifier, ParameterError>,
+    airport2: Result<Identifier, ParameterError>,
+    carrier: Option<Result<Identifier, ParameterError>>,
+) -> Result<Result<Option<Json<ConnectionStatistics>>, ParameterError>, Error> {
+    let (airport1, airport2, carrier) = match check_parameters(airport1, airport2, carrier) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let data = get_statistics_connection_data(&conn, &airport1, &airport2, carrier.as_ref())?;
+    Ok(Ok(data.map(Json)))
+}
+
+/// Get the CSV representation of the connection statistics between 2 airports.
+/// Each row holds the descriptive statistics of one of the delay columns.
+#[get(
+    "/connection?<airport1>&<airport2>&<carrier>",
+    format = "text/csv",
+    rank = 2
+)]
+pub fn get_statistics_connection_csv(
+    conn: CorgisDbConn,
+    airport1: Result<Identifier, ParameterError>,
+    airport2: Result<Identifier, ParameterError>,
+    carrier: Option<Result<Identifier, ParameterError>>,
+) -> Result<Result<Option<Csv<ConnectionStatistics>>, ParameterError>, Error> {
+    fn convertor(statistics: &ConnectionStatistics) -> String {
+        #[derive(Serialize)]
+        struct Record<'a> {
+            statistic: &'a str,
+            count: usize,
+            mean: Option<f64>,
+            median: Option<f64>,
+            standard_deviation: Option<f64>,
+            min: Option<i64>,
+            max: Option<i64>,
+        }
+
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        for (statistic, descriptive) in &[
+            (
+                "minutes_delayed_carrier",
+                &statistics.minutes_delayed_carrier,
+            ),
+            (
+                "minutes_delayed_late_aircraft",
+                &statistics.minutes_delayed_late_aircraft,
+            ),
+        ] {
+            wtr.serialize(Record {
+                statistic,
+                count: descriptive.count,
+                mean: descriptive.mean,
+                median: descriptive.median,
+                standard_deviation: descriptive.standard_deviation,
+                min: descriptive.min,
+                max: descriptive.max,
+            })
+            .unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let (airport1, airport2, carrier) = match check_parameters(airport1, airport2, carrier) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let data = get_statistics_connection_data(&conn, &airport1, &airport2, carrier.as_ref())?;
+    Ok(Ok(data.map(|data| Csv(data, convertor))))
+}
+
+/// Get the HAL representation of the connection statistics between 2 airports.
+#[get(
+    "/connection?<airport1>&<airport2>&<carrier>",
+    format = "application/hal+json",
+    rank = 3
+)]
+pub fn get_statistics_connection_hal(
+    conn: CorgisDbConn,
+    airport1: Result<Identifier, ParameterError>,
+    airport2: Result<Identifier, ParameterError>,
+    carrier: Option<Result<Identifier, ParameterError>>,
+) -> Result<Result<Option<Json<HalResource>>, ParameterError>, Error> {
+    let (airport1, airport2, carrier) = match check_parameters(airport1, airport2, carrier) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let data = get_statistics_connection_data(&conn, &airport1, &airport2, carrier.as_ref())?;
+    match data {
+        None => Ok(Ok(None)),
+        Some(data) => {
+            let mut query = format!("airport1={}&airport2={}", airport1, airport2);
+            if let Some(carrier) = &carrier {
+                query.push_str(&format!("&carrier={}", carrier));
+            }
+            let mut result = HalResource::new(&data)
+                .with_link("self", format!("/statistics/connection?{}", query))
+                .with_link("airport1", format!("/airports/{}", data.airport1))
+                .with_link("airport2", format!("/airports/{}", data.airport2));
+            for carrier_id in data.carriers.iter().flatten() {
+                result = result.with_link("carrier", format!("/carriers/{}", carrier_id));
+            }
+
+            Ok(Ok(Some(Json(result))))
+        }
+    }
+}
+
+/// Get the default representation of the connection statistics between 2
+/// airports. This is executed if the other routes are not matched.
+#[get("/connection?<airport1>&<airport2>&<carrier>", rank = 4)]
+pub fn get_statistics_connection_default(
+    conn: CorgisDbConn,
+    airport1: Result<Identifier, ParameterError>,
+    airport2: Result<Identifier, ParameterError>,
+    carrier: Option<Result<Identifier, ParameterError>>,
+) -> Result<Result<Option<Json<ConnectionStatistics>>, ParameterError>, diesel::result::Error> {
+    get_statistics_connection_json(conn, airport1, airport2, carrier)
+}
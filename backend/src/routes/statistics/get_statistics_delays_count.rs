@@ -0,0 +1,243 @@
+//! A module dealing with the `get_statistics_delays_count` routes.
+
+use super::filter::{StatisticsFilter, StatisticsFilterForm};
+use super::reason::Reasons;
+use super::sort::{check_sort, Sort};
+use super::views::StatisticsDelaysCount;
+
+use crate::routes::pagination::{Cursor, Page, PageRequest, PagedCsv};
+use crate::routes::parameters::{join_query, ParameterError};
+use crate::CorgisDbConn;
+use diesel::{prelude::*, result::Error};
+use rayon::prelude::*;
+use rocket::{get, http::RawStr, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+
+fn get_statistics_delays_count_data(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    sort: Option<&Sort>,
+    reasons: Option<&Reasons>,
+    average: Option<bool>,
+    page: PageRequest,
+) -> Result<Page<StatisticsDelaysCount>, Error> {
+    use crate::database::models;
+
+    let reasons = reasons.cloned().unwrap_or_default();
+
+    let page = filter.load_page(conn, sort, page, |query| {
+        query.load::<models::Statistics>(conn)
+    })?;
+
+    let items = page
+        .items
+        .into_par_iter()
+        .map(|statistics| {
+            StatisticsDelaysCount::new(statistics, &reasons, average.unwrap_or(false))
+        })
+        .collect();
+
+    Ok(Page {
+        pagination: page.pagination,
+        items,
+    })
+}
+
+/// Check the `sort` and `reason` parameters of a request.
+fn check_parameters(
+    sort: Option<Result<Sort, ParameterError>>,
+    reason: Option<Result<Reasons, ParameterError>>,
+    cursor: Option<Cursor>,
+) -> Result<(Option<Sort>, Option<Reasons>), ParameterError> {
+    Ok((check_sort(sort, cursor)?, reason.transpose()?))
+}
+
+/// Get the JSON representation of the number of delays statistics in the
+/// database, a page at a time.
+#[get(
+    "/delays-count?<reason>&<average>&<sort>&<limit>&<offset>&<cursor>&<filter..>",
+    format = "application/json",
+    rank = 1
+)]
+#[allow(clippy::too_many_arguments)]
+pub fn get_statistics_delays_count_json(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    sort: Option<Result<Sort, ParameterError>>,
+    reason: Option<Result<Reasons, ParameterError>>,
+    average: Option<bool>,
+    limit: Option<Result<usize, &RawStr>>,
+    offset: Option<Result<usize, &RawStr>>,
+    cursor: Option<Result<Cursor, ParameterError>>,
+) -> Result<Result<Json<Page<StatisticsDelaysCount>>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let page_request = match PageRequest::check(limit, offset, cursor) {
+        Ok(page_request) => page_request,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (sort, reason) = match check_parameters(sort, reason, page_request.cursor) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    get_statistics_delays_count_data(
+        &conn,
+        &filter,
+        sort.as_ref(),
+        reason.as_ref(),
+        average,
+        page_request,
+    )
+    .map(|data| Ok(Json(data)))
+}
+
+/// Get the CSV representation of the number of delays statistics in the
+/// database, a page at a time. The pagination is described by the headers of
+/// the response.
+#[get(
+    "/delays-count?<reason>&<average>&<sort>&<limit>&<offset>&<cursor>&<filter..>",
+    format = "text/csv",
+    rank = 2
+)]
+#[allow(clippy::too_many_arguments)]
+pub fn get_statistics_delays_count_csv(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    sort: Option<Result<Sort, ParameterError>>,
+    reason: Option<Result<Reasons, ParameterError>>,
+    average: Option<bool>,
+    limit: Option<Result<usize, &RawStr>>,
+    offset: Option<Result<usize, &RawStr>>,
+    cursor: Option<Result<Cursor, ParameterError>>,
+) -> Result<Result<PagedCsv<Vec<StatisticsDelaysCount>>, ParameterError>, Error> {
+    fn convertor(statistics_set: &Vec<StatisticsDelaysCount>) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        for statistics in statistics_set {
+            wtr.serialize(statistics).unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let page_request = match PageRequest::check(limit, offset, cursor) {
+        Ok(page_request) => page_request,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (sort, reason) = match check_parameters(sort, reason, page_request.cursor) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![
+        filter.to_string(),
+        sort.as_ref().map_or_else(String::new, Sort::to_query),
+        reason.as_ref().map_or_else(String::new, Reasons::to_query),
+        average.map_or_else(String::new, |average| format!("average={}", average)),
+    ]);
+    let page = get_statistics_delays_count_data(
+        &conn,
+        &filter,
+        sort.as_ref(),
+        reason.as_ref(),
+        average,
+        page_request,
+    )?;
+
+    Ok(Ok(page.pagination.csv(
+        "/statistics/delays-count",
+        &query,
+        Csv(page.items, convertor),
+    )))
+}
+
+/// Get the HAL representation of the number of delays statistics in the
+/// database, a page at a time.
+#[get(
+    "/delays-count?<reason>&<average>&<sort>&<limit>&<offset>&<cursor>&<filter..>",
+    format = "application/hal+json",
+    rank = 3
+)]
+#[allow(clippy::too_many_arguments)]
+pub fn get_statistics_delays_count_hal(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    sort: Option<Result<Sort, ParameterError>>,
+    reason: Option<Result<Reasons, ParameterError>>,
+    average: Option<bool>,
+    limit: Option<Result<usize, &RawStr>>,
+    offset: Option<Result<usize, &RawStr>>,
+    cursor: Option<Result<Cursor, ParameterError>>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let page_request = match PageRequest::check(limit, offset, cursor) {
+        Ok(page_request) => page_request,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (sort, reason) = match check_parameters(sort, reason, page_request.cursor) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![
+        filter.to_string(),
+        sort.as_ref().map_or_else(String::new, Sort::to_query),
+        reason.as_ref().map_or_else(String::new, Reasons::to_query),
+        average.map_or_else(String::new, |average| format!("average={}", average)),
+    ]);
+    let page = get_statistics_delays_count_data(
+        &conn,
+        &filter,
+        sort.as_ref(),
+        reason.as_ref(),
+        average,
+        page_request,
+    )?;
+    let statistics = page
+        .items
+        .into_par_iter()
+        .map(|data| HalResource::new(&data).with_link("super", format!("/statistics/{}", data.id)))
+        .collect();
+
+    let result = page
+        .pagination
+        .hal("/statistics/delays-count", &query, "statistics", statistics);
+
+    Ok(Ok(Json(result)))
+}
+
+/// Get the default representation of the number of delays statistics in the data store. This is
+/// executed if the other routes are not matched.
+#[get(
+    "/delays-count?<reason>&<average>&<sort>&<limit>&<offset>&<cursor>&<filter..>",
+    rank = 4
+)]
+#[allow(clippy::too_many_arguments)]
+pub fn get_statistics_delays_count_default(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    sort: Option<Result<Sort, ParameterError>>,
+    reason: Option<Result<Reasons, ParameterError>>,
+    average: Option<bool>,
+    limit: Option<Result<usize, &RawStr>>,
+    offset: Option<Result<usize, &RawStr>>,
+    cursor: Option<Result<Cursor, ParameterError>>,
+) -> Result<Result<Json<Page<StatisticsDelaysCount>>, ParameterError>, diesel::result::Error> {
+    get_statistics_delays_count_json(conn, filter, sort, reason, average, limit, offset, cursor)
+}
@@ -1,185 +1,114 @@
 //! A module dealing with the `get_statistics_minutes_delayed` routes.
 
+use super::filter::{StatisticsFilter, StatisticsFilterForm};
+use super::reason::Reasons;
+use super::sort::{check_sort, Sort};
 use super::views::StatisticsMinutesDelayed;
 
+use crate::routes::pagination::{Cursor, Page, PageRequest, PagedCsv};
+use crate::routes::parameters::{join_query, ParameterError};
 use crate::CorgisDbConn;
-use diesel::{dsl::*, prelude::*, result::Error};
+use diesel::{prelude::*, result::Error};
 use rayon::prelude::*;
-use rocket::get;
+use rocket::{get, http::RawStr, request::LenientForm};
 use rocket_contrib::json::Json;
 use rocket_contrib_local::csv::Csv;
 use rustic_hal::HalResource;
 
-const LIMIT: usize = 100;
-const OFFSET: usize = 0;
-
 fn get_statistics_minutes_delayed_data(
     conn: &diesel::PgConnection,
-    carrier: Option<i64>,
-    airport: Option<i64>,
-    month: Option<u32>,
-    reason: Option<String>,
-    limit: Option<usize>,
-    offset: Option<usize>,
-) -> Result<Vec<StatisticsMinutesDelayed>, Error> {
-    use crate::database::{models, schema};
-    use chrono::Datelike;
-
-    let offset = offset.unwrap_or(OFFSET) as i64;
-    let limit = limit.unwrap_or(LIMIT) as i64;
-
-    let statistics: Vec<models::Statistics> = match (carrier, airport) {
-        (None, None) => match schema::statistics::table
-            .limit(limit)
-            .offset(offset)
-            .load(conn)
-            .optional()?
-        {
-            Some(statistics_data) => statistics_data,
-            None => return Ok(Vec::new()),
-        },
-        (None, Some(airport)) => {
-            let airport_id: i64 = match schema::airports::table
-                .find(airport)
-                .select(schema::airports::id)
-                .first(conn)
-                .optional()?
-            {
-                None => return Ok(Vec::new()),
-                Some(airport_id) => airport_id,
-            };
-            match schema::statistics::table
-                .filter(schema::statistics::airport_id.eq(airport_id))
-                .limit(limit)
-                .offset(offset)
-                .load::<models::Statistics>(conn)
-                .optional()?
-            {
-                Some(data) => data,
-                None => Vec::new(),
-            }
-        }
-        (Some(carrier), None) => {
-            let carrier_id: i64 = match schema::carriers::table
-                .find(carrier)
-                .select(schema::carriers::id)
-                .first(conn)
-                .optional()?
-            {
-                None => return Ok(Vec::new()),
-                Some(carrier_id) => carrier_id,
-            };
-            match schema::statistics::table
-                .filter(schema::statistics::carrier_id.eq(carrier_id))
-                .limit(limit)
-                .offset(offset)
-                .load::<models::Statistics>(conn)
-                .optional()?
-            {
-                Some(data) => data,
-                None => Vec::new(),
-            }
-        }
-        (Some(carrier), Some(airport)) => {
-            let airport_id: i64 = match schema::airports::table
-                .find(airport)
-                .select(schema::airports::id)
-                .first(conn)
-                .optional()?
-            {
-                None => return Ok(Vec::new()),
-                Some(airport_id) => airport_id,
-            };
-
-            let carrier_id: i64 = match schema::carriers::table
-                .find(carrier)
-                .select(schema::carriers::id)
-                .first(conn)
-                .optional()?
-            {
-                None => return Ok(Vec::new()),
-                Some(carrier_id) => carrier_id,
-            };
-
-            match schema::statistics::table
-                .filter(
-                    schema::statistics::airport_id
-                        .eq(airport_id)
-                        .and(schema::statistics::carrier_id.eq(carrier_id)),
-                )
-                .limit(limit)
-                .offset(offset)
-                .load::<models::Statistics>(conn)
-                .optional()?
-            {
-                Some(data) => data,
-                None => Vec::new(),
-            }
-        }
-    };
+    filter: &StatisticsFilter,
+    sort: Option<&Sort>,
+    reasons: Option<&Reasons>,
+    page: PageRequest,
+) -> Result<Page<StatisticsMinutesDelayed>, Error> {
+    use crate::database::models;
 
-    let statistics = match month {
-        Some(month) => statistics
-            .into_par_iter()
-            .filter(|record| record.time.month() == month)
-            .collect(),
-        None => statistics,
-    };
+    let reasons = reasons.cloned().unwrap_or_default();
 
-    Ok(statistics
+    let page = filter.load_page(conn, sort, page, |query| {
+        query.load::<models::Statistics>(conn)
+    })?;
+
+    let items = page
+        .items
         .into_par_iter()
-        .map(|statistics| {
-            let mut statistics = StatisticsMinutesDelayed::from(statistics);
-            if let Some(reason) = reason.clone() {
-                match reason.as_str() {
-                    "carrier_specific" => {
-                        statistics.minutes_delayed_national_aviation_system = None;
-                        statistics.minutes_delayed_security = None;
-                        statistics.minutes_delayed_weather = None;
-                        statistics.minutes_delayed_total = None;
-                    }
-                    _ => (),
-                }
-            }
-            statistics
-        })
-        .collect())
+        .map(|statistics| StatisticsMinutesDelayed::new(statistics, &reasons))
+        .collect();
+
+    Ok(Page {
+        pagination: page.pagination,
+        items,
+    })
 }
 
-/// Get the JSON representation of the minutes delayed statistics in the database.
+/// Check the `sort` and `reason` parameters of a request.
+fn check_parameters(
+    sort: Option<Result<Sort, ParameterError>>,
+    reason: Option<Result<Reasons, ParameterError>>,
+    cursor: Option<Cursor>,
+) -> Result<(Option<Sort>, Option<Reasons>), ParameterError> {
+    Ok((check_sort(sort, cursor)?, reason.transpose()?))
+}
+
+/// Get the JSON representation of the minutes delayed statistics in the
+/// database, a page at a time.
 #[get(
-    "/minutes-delayed?<carrier>&<airport>&<month>&<reason>&<limit>&<offset>",
+    "/minutes-delayed?<reason>&<sort>&<limit>&<offset>&<cursor>&<filter..>",
     format = "application/json",
     rank = 1
 )]
 pub fn get_statistics_minutes_delayed_json(
     conn: CorgisDbConn,
-    carrier: Option<i64>,
-    airport: Option<i64>,
-    month: Option<u32>,
-    reason: Option<String>,
-    limit: Option<usize>,
-    offset: Option<usize>,
-) -> Result<Json<Vec<StatisticsMinutesDelayed>>, Error> {
-    get_statistics_minutes_delayed_data(&conn, carrier, airport, month, reason, limit, offset)
-        .map(Json)
+    filter: LenientForm<StatisticsFilterForm>,
+    sort: Option<Result<Sort, ParameterError>>,
+    reason: Option<Result<Reasons, ParameterError>>,
+    limit: Option<Result<usize, &RawStr>>,
+    offset: Option<Result<usize, &RawStr>>,
+    cursor: Option<Result<Cursor, ParameterError>>,
+) -> Result<Result<Json<Page<StatisticsMinutesDelayed>>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let page_request = match PageRequest::check(limit, offset, cursor) {
+        Ok(page_request) => page_request,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (sort, reason) = match check_parameters(sort, reason, page_request.cursor) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    get_statistics_minutes_delayed_data(
+        &conn,
+        &filter,
+        sort.as_ref(),
+        reason.as_ref(),
+        page_request,
+    )
+    .map(|data| Ok(Json(data)))
 }
 
-/// Get the CSV representation of the minutes_delayed statistics in the database.
+/// Get the CSV representation of the minutes_delayed statistics in the
+/// database, a page at a time. The pagination is described by the headers of
+/// the response.
 #[get(
-    "/minutes-delayed?<carrier>&<airport>&<month>&<reason>&<limit>&<offset>",
+    "/minutes-delayed?<reason>&<sort>&<limit>&<offset>&<cursor>&<filter..>",
     format = "text/csv",
     rank = 2
 )]
 pub fn get_statistics_minutes_delayed_csv(
     conn: CorgisDbConn,
-    carrier: Option<i64>,
-    airport: Option<i64>,
-    month: Option<u32>,
-    reason: Option<String>,
-    limit: Option<usize>,
-    offset: Option<usize>,
-) -> Result<Csv<Vec<StatisticsMinutesDelayed>>, Error> {
+    filter: LenientForm<StatisticsFilterForm>,
+    sort: Option<Result<Sort, ParameterError>>,
+    reason: Option<Result<Reasons, ParameterError>>,
+    limit: Option<Result<usize, &RawStr>>,
+    offset: Option<Result<usize, &RawStr>>,
+    cursor: Option<Result<Cursor, ParameterError>>,
+) -> Result<Result<PagedCsv<Vec<StatisticsMinutesDelayed>>, ParameterError>, Error> {
     fn convertor(statistics_set: &Vec<StatisticsMinutesDelayed>) -> String {
         let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
         for statistics in statistics_set {
@@ -188,50 +117,114 @@ pub fn get_statistics_minutes_delayed_csv(
         String::from_utf8(wtr.into_inner().unwrap()).unwrap()
     };
 
-    get_statistics_minutes_delayed_data(&conn, carrier, airport, month, reason, limit, offset)
-        .map(|data| Csv(data, convertor))
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let page_request = match PageRequest::check(limit, offset, cursor) {
+        Ok(page_request) => page_request,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (sort, reason) = match check_parameters(sort, reason, page_request.cursor) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![
+        filter.to_string(),
+        sort.as_ref().map_or_else(String::new, Sort::to_query),
+        reason.as_ref().map_or_else(String::new, Reasons::to_query),
+    ]);
+    let page = get_statistics_minutes_delayed_data(
+        &conn,
+        &filter,
+        sort.as_ref(),
+        reason.as_ref(),
+        page_request,
+    )?;
+
+    Ok(Ok(page.pagination.csv(
+        "/statistics/minutes-delayed",
+        &query,
+        Csv(page.items, convertor),
+    )))
 }
 
-/// Get the HAL representation of the minutes delayed statistics in the database.
+/// Get the HAL representation of the minutes delayed statistics in the
+/// database, a page at a time.
 #[get(
-    "/minutes-delayed?<carrier>&<airport>&<month>&<reason>&<limit>&<offset>",
+    "/minutes-delayed?<reason>&<sort>&<limit>&<offset>&<cursor>&<filter..>",
     format = "application/hal+json",
     rank = 3
 )]
 pub fn get_statistics_minutes_delayed_hal(
     conn: CorgisDbConn,
-    carrier: Option<i64>,
-    airport: Option<i64>,
-    month: Option<u32>,
-    reason: Option<String>,
-    limit: Option<usize>,
-    offset: Option<usize>,
-) -> Result<Json<Vec<HalResource>>, Error> {
-    let result =
-        get_statistics_minutes_delayed_data(&conn, carrier, airport, month, reason, limit, offset)?
-            .into_par_iter()
-            .map(|data| {
-                HalResource::new(&data).with_link("super", format!("/statistics/{}", data.id))
-            })
-            .collect();
-
-    Ok(Json(result))
+    filter: LenientForm<StatisticsFilterForm>,
+    sort: Option<Result<Sort, ParameterError>>,
+    reason: Option<Result<Reasons, ParameterError>>,
+    limit: Option<Result<usize, &RawStr>>,
+    offset: Option<Result<usize, &RawStr>>,
+    cursor: Option<Result<Cursor, ParameterError>>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let page_request = match PageRequest::check(limit, offset, cursor) {
+        Ok(page_request) => page_request,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let (sort, reason) = match check_parameters(sort, reason, page_request.cursor) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![
+        filter.to_string(),
+        sort.as_ref().map_or_else(String::new, Sort::to_query),
+        reason.as_ref().map_or_else(String::new, Reasons::to_query),
+    ]);
+    let page = get_statistics_minutes_delayed_data(
+        &conn,
+        &filter,
+        sort.as_ref(),
+        reason.as_ref(),
+        page_request,
+    )?;
+    let statistics = page
+        .items
+        .into_par_iter()
+        .map(|data| HalResource::new(&data).with_link("super", format!("/statistics/{}", data.id)))
+        .collect();
+
+    let result = page.pagination.hal(
+        "/statistics/minutes-delayed",
+        &query,
+        "statistics",
+        statistics,
+    );
+
+    Ok(Ok(Json(result)))
 }
 
 /// Get the default representation of the minutes delayed statistics in the data store. This is
 /// executed if the other routes are not matched.
 #[get(
-    "/minutes-delayed?<carrier>&<airport>&<month>&<reason>&<limit>&<offset>",
+    "/minutes-delayed?<reason>&<sort>&<limit>&<offset>&<cursor>&<filter..>",
     rank = 4
 )]
 pub fn get_statistics_minutes_delayed_default(
     conn: CorgisDbConn,
-    carrier: Option<i64>,
-    airport: Option<i64>,
-    month: Option<u32>,
-    reason: Option<String>,
-    limit: Option<usize>,
-    offset: Option<usize>,
-) -> Result<Json<Vec<StatisticsMinutesDelayed>>, diesel::result::Error> {
-    get_statistics_minutes_delayed_json(conn, carrier, airport, month, reason, limit, offset)
+    filter: LenientForm<StatisticsFilterForm>,
+    sort: Option<Result<Sort, ParameterError>>,
+    reason: Option<Result<Reasons, ParameterError>>,
+    limit: Option<Result<usize, &RawStr>>,
+    offset: Option<Result<usize, &RawStr>>,
+    cursor: Option<Result<Cursor, ParameterError>>,
+) -> Result<Result<Json<Page<StatisticsMinutesDelayed>>, ParameterError>, diesel::result::Error> {
+    get_statistics_minutes_delayed_json(conn, filter, sort, reason, limit, offset, cursor)
 }
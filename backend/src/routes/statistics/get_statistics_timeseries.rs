@@ -0,0 +1,131 @@
+//! A module dealing with the `get_statistics_timeseries` routes.
+
+use super::filter::{StatisticsFilter, StatisticsFilterForm};
+use super::timeseries::{load_timeseries, Fill, SeriesMetric, SeriesParameters};
+use super::views::TimeSeries;
+
+use crate::routes::parameters::{join_query, ParameterError};
+use crate::CorgisDbConn;
+use diesel::result::Error;
+use rocket::{get, request::LenientForm};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::Csv;
+use rustic_hal::HalResource;
+
+fn get_statistics_timeseries_data(
+    conn: &diesel::PgConnection,
+    filter: &StatisticsFilter,
+    parameters: &SeriesParameters,
+) -> Result<TimeSeries, Error> {
+    load_timeseries(conn, filter.query(conn)?, parameters)
+}
+
+/// Get the JSON representation of the monthly time series of the statistics.
+#[get(
+    "/timeseries?<metric>&<fill>&<rolling>&<yoy>&<filter..>",
+    format = "application/json",
+    rank = 1
+)]
+pub fn get_statistics_timeseries_json(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    fill: Option<Result<Fill, ParameterError>>,
+    rolling: Option<usize>,
+    yoy: Option<bool>,
+) -> Result<Result<Json<TimeSeries>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match SeriesParameters::check(metric, fill, rolling, yoy) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    get_statistics_timeseries_data(&conn, &filter, &parameters).map(|data| Ok(Json(data)))
+}
+
+/// Get the CSV representation of the monthly time series of the statistics,
+/// a row for each month.
+#[get(
+    "/timeseries?<metric>&<fill>&<rolling>&<yoy>&<filter..>",
+    format = "text/csv",
+    rank = 2
+)]
+pub fn get_statistics_timeseries_csv(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    fill: Option<Result<Fill, ParameterError>>,
+    rolling: Option<usize>,
+    yoy: Option<bool>,
+) -> Result<Result<Csv<TimeSeries>, ParameterError>, Error> {
+    fn convertor(series: &TimeSeries) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        for point in &series.points {
+            wtr.serialize(point).unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match SeriesParameters::check(metric, fill, rolling, yoy) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    get_statistics_timeseries_data(&conn, &filter, &parameters).map(|data| Ok(Csv(data, convertor)))
+}
+
+/// Get the HAL representation of the monthly time series of the statistics.
+#[get(
+    "/timeseries?<metric>&<fill>&<rolling>&<yoy>&<filter..>",
+    format = "application/hal+json",
+    rank = 3
+)]
+pub fn get_statistics_timeseries_hal(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    fill: Option<Result<Fill, ParameterError>>,
+    rolling: Option<usize>,
+    yoy: Option<bool>,
+) -> Result<Result<Json<HalResource>, ParameterError>, Error> {
+    let filter = match filter.check() {
+        Ok(filter) => filter,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let parameters = match SeriesParameters::check(metric, fill, rolling, yoy) {
+        Ok(parameters) => parameters,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let query = join_query(vec![filter.to_string(), parameters.to_query()]);
+    let data = get_statistics_timeseries_data(&conn, &filter, &parameters)?;
+    let result = HalResource::new(&data)
+        .with_link("self", format!("/statistics/timeseries?{}", query))
+        .with_link("statistics", format!("/statistics?{}", filter));
+
+    Ok(Ok(Json(result)))
+}
+
+/// Get the default representation of the monthly time series of the
+/// statistics. This is executed if the other routes are not matched.
+#[get("/timeseries?<metric>&<fill>&<rolling>&<yoy>&<filter..>", rank = 4)]
+pub fn get_statistics_timeseries_default(
+    conn: CorgisDbConn,
+    filter: LenientForm<StatisticsFilterForm>,
+    metric: Option<Result<SeriesMetric, ParameterError>>,
+    fill: Option<Result<Fill, ParameterError>>,
+    rolling: Option<usize>,
+    yoy: Option<bool>,
+) -> Result<Result<Json<TimeSeries>, ParameterError>, diesel::result::Error> {
+    get_statistics_timeseries_json(conn, filter, metric, fill, rolling, yoy)
+}
@@ -1,14 +1,25 @@
 //! A module dealing with the "/statistics" routes.
 
+mod aggregate;
+pub mod columns;
 mod delete_statistic;
+mod fields;
+pub mod filter;
 mod get_statistic;
 mod get_statistics;
+mod get_statistics_aggregate;
 mod get_statistics_connection;
+mod get_statistics_delays_count;
 mod get_statistics_flights;
 mod get_statistics_minutes_delayed;
+mod get_statistics_timeseries;
 mod patch_statistic;
 mod post_statistics;
+mod post_statistics_batch;
 mod put_statistic;
+mod reason;
+mod sort;
+pub mod timeseries;
 mod views;
 
 use rocket::routes;
@@ -23,9 +34,13 @@ pub fn mount(rocket: rocket::Rocket) -> rocket::Rocket {
             get_statistics::get_statistics_hal,
             get_statistics::get_statistics_default,
             post_statistics::post_statistic_json,
-            // post_statistics::post_statistic_csv,
+            post_statistics::post_statistic_csv,
             post_statistics::post_statistic_hal,
             post_statistics::post_statistic_default,
+            post_statistics_batch::post_statistics_batch_json,
+            post_statistics_batch::post_statistics_batch_csv,
+            post_statistics_batch::post_statistics_batch_hal,
+            post_statistics_batch::post_statistics_batch_default,
             get_statistic::get_statistics_json,
             get_statistic::get_statistics_csv,
             get_statistic::get_statistics_hal,
@@ -34,10 +49,10 @@ pub fn mount(rocket: rocket::Rocket) -> rocket::Rocket {
             delete_statistic::delete_statistics_csv,
             delete_statistic::delete_statistics_hal,
             delete_statistic::delete_statistics_default,
-            // get_statistics_connection::get_statistics_connection_json,
-            // get_statistics_connection::get_statistics_connection_csv,
-            // get_statistics_connection::get_statistics_connection_hal,
-            // get_statistics_connection::get_statistics_connection_default,
+            get_statistics_connection::get_statistics_connection_json,
+            get_statistics_connection::get_statistics_connection_csv,
+            get_statistics_connection::get_statistics_connection_hal,
+            get_statistics_connection::get_statistics_connection_default,
             get_statistics_flights::get_statistics_flights_json,
             get_statistics_flights::get_statistics_flights_csv,
             get_statistics_flights::get_statistics_flights_hal,
@@ -46,15 +61,26 @@ pub fn mount(rocket: rocket::Rocket) -> rocket::Rocket {
             get_statistics_minutes_delayed::get_statistics_minutes_delayed_csv,
             get_statistics_minutes_delayed::get_statistics_minutes_delayed_hal,
             get_statistics_minutes_delayed::get_statistics_minutes_delayed_default,
-            // patch_statistic::patch_statistics_json,
-            // patch_statistic::patch_statistics_csv,
-            // patch_statistic::patch_statistics_hal,
-            // patch_statistic::patch_statistics_default,
-
-            // put_statistic::put_statistics_json,
-            // put_statistic::put_statistics_csv,
-            // put_statistic::put_statistics_hal,
-            // put_statistic::put_statistics_default,
+            get_statistics_delays_count::get_statistics_delays_count_json,
+            get_statistics_delays_count::get_statistics_delays_count_csv,
+            get_statistics_delays_count::get_statistics_delays_count_hal,
+            get_statistics_delays_count::get_statistics_delays_count_default,
+            get_statistics_aggregate::get_statistics_aggregate_json,
+            get_statistics_aggregate::get_statistics_aggregate_csv,
+            get_statistics_aggregate::get_statistics_aggregate_hal,
+            get_statistics_aggregate::get_statistics_aggregate_default,
+            get_statistics_timeseries::get_statistics_timeseries_json,
+            get_statistics_timeseries::get_statistics_timeseries_csv,
+            get_statistics_timeseries::get_statistics_timeseries_hal,
+            get_statistics_timeseries::get_statistics_timeseries_default,
+            patch_statistic::patch_statistics_json,
+            patch_statistic::patch_statistics_csv,
+            patch_statistic::patch_statistics_hal,
+            patch_statistic::patch_statistics_default,
+            put_statistic::put_statistics_json,
+            put_statistic::put_statistics_csv,
+            put_statistic::put_statistics_hal,
+            put_statistic::put_statistics_default,
         ),
     )
 }
@@ -1 +1,118 @@
+//! A module defining the `patch_statistic` routes.
 
+use super::views::Statistics;
+use super::views::UpdateStatistics;
+
+use crate::CorgisDbConn;
+use diesel::{prelude::*, result::Error};
+use rocket::patch;
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::{Csv, CsvError};
+use rustic_hal::HalResource;
+
+fn patch_statistics_data(
+    conn: &diesel::PgConnection,
+    id: i64,
+    update_statistics: UpdateStatistics,
+) -> Result<Option<Statistics>, Error> {
+    use crate::database::models;
+    use crate::database::schema::statistics::dsl::statistics;
+
+    let statistics_data = match diesel::update(statistics.find(id))
+        .set(&models::UpdateStatistics::from(&update_statistics))
+        .get_result::<models::Statistics>(conn)
+    {
+        // An update without any changes leaves the statistics as they are.
+        Err(Error::QueryBuilderError(_)) => statistics.find(id).first(conn),
+        result => result,
+    };
+
+    Ok(statistics_data.optional()?.map(Statistics::from))
+}
+
+/// Get the JSON representation of the updated set of statistics in the
+/// database.
+#[patch(
+    "/<statistics>",
+    format = "application/json",
+    data = "<update_statistics>",
+    rank = 1
+)]
+pub fn patch_statistics_json(
+    conn: CorgisDbConn,
+    statistics: i64,
+    update_statistics: Json<UpdateStatistics>,
+) -> Result<Option<Json<Statistics>>, Error> {
+    Ok(patch_statistics_data(&conn, statistics, update_statistics.into_inner())?.map(Json))
+}
+
+/// Get the CSV representation of the updated set of statistics in the
+/// database.
+#[patch(
+    "/<statistics>",
+    format = "text/csv",
+    data = "<update_statistics>",
+    rank = 2
+)]
+pub fn patch_statistics_csv(
+    conn: CorgisDbConn,
+    statistics: i64,
+    update_statistics: Result<Csv<UpdateStatistics>, CsvError>,
+) -> Result<Result<Option<Csv<Statistics>>, CsvError>, Error> {
+    fn convertor(statistics: &Statistics) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        wtr.serialize(statistics).unwrap();
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let update_statistics = match update_statistics {
+        Ok(update_statistics) => update_statistics.into_inner(),
+        Err(error) => return Ok(Err(error)),
+    };
+
+    Ok(Ok(patch_statistics_data(
+        &conn,
+        statistics,
+        update_statistics,
+    )?
+    .map(|data| Csv(data, convertor))))
+}
+
+/// Get the HAL representation of the updated set of statistics in the
+/// database.
+#[patch(
+    "/<statistics>",
+    format = "application/hal+json",
+    data = "<update_statistics>",
+    rank = 3
+)]
+pub fn patch_statistics_hal(
+    conn: CorgisDbConn,
+    statistics: i64,
+    update_statistics: Json<UpdateStatistics>,
+) -> Result<Option<Json<HalResource>>, Error> {
+    let data = patch_statistics_data(&conn, statistics, update_statistics.into_inner())?;
+    match data {
+        None => Ok(None),
+        Some(data) => {
+            let result = HalResource::new(&data)
+                .with_link("self", format!("/statistics/{}", data.id))
+                .with_link("delete", format!("/statistics/{}", data.id))
+                .with_link("patch", format!("/statistics/{}", data.id))
+                .with_link("put", format!("/statistics/{}", data.id));
+
+            Ok(Some(Json(result)))
+        }
+    }
+}
+
+/// Get the default representation of the updated set of statistics in the
+/// data store. This is executed if the other routes are not matched.
+#[patch("/<statistics>", data = "<update_statistics>", rank = 4)]
+pub fn patch_statistics_default(
+    conn: CorgisDbConn,
+    statistics: i64,
+    update_statistics: Json<UpdateStatistics>,
+) -> Result<Option<Json<Statistics>>, diesel::result::Error> {
+    patch_statistics_json(conn, statistics, update_statistics)
+}
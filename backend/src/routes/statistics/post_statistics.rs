@@ -7,37 +7,38 @@ use crate::CorgisDbConn;
 use diesel::{prelude::*, result::Error};
 use rocket::post;
 use rocket_contrib::json::Json;
-use rocket_contrib_local::csv::Csv;
+use rocket_contrib_local::csv::{Csv, CsvError};
 use rustic_hal::HalResource;
 
 fn post_statistic_data(
     conn: &diesel::PgConnection,
     statistics: NewStatistics,
 ) -> Result<Statistics, Error> {
-    let statistics = crate::database::models::NewStatistics {
-        flights_cancelled: &statistics.flights_cancelled,
-        flights_delayed: &statistics.flights_delayed,
-        flights_diverted: &statistics.flights_diverted,
-        flights_on_time: &statistics.flights_on_time,
-        minutes_delayed_carrier: &statistics.minutes_delayed_carrier,
-        minutes_delayed_late_aircraft: &statistics.minutes_delayed_late_aircraft,
-        minutes_delayed_national_aviation_system: &statistics
-            .minutes_delayed_national_aviation_system,
-        minutes_delayed_security: &statistics.minutes_delayed_security,
-        minutes_delayed_weather: &statistics.minutes_delayed_weather,
-        number_of_delays_carrier: &statistics.number_of_delays_carrier,
-        number_of_delays_late_aircraft: &statistics.number_of_delays_late_aircraft,
-        number_of_delays_national_aviation_system: &statistics
-            .number_of_delays_national_aviation_system,
-        number_of_delays_security: &statistics.number_of_delays_security,
-        number_of_delays_weather: &statistics.number_of_delays_weather,
-        time: &statistics.time,
-        carrier_id: &statistics.carrier_id,
-        airport_id: &statistics.airport_id,
-    };
+    let statistics = crate::database::models::NewStatistics::from(&statistics);
     crate::database::create_statistics(conn, statistics).map(Statistics::from)
 }
 
+fn post_statistics_data(
+    conn: &diesel::PgConnection,
+    statistics: Vec<NewStatistics>,
+) -> Result<Vec<Statistics>, Error> {
+    use crate::database::{models, schema};
+
+    if statistics.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let statistics: Vec<models::NewStatistics> =
+        statistics.iter().map(models::NewStatistics::from).collect();
+
+    Ok(diesel::insert_into(schema::statistics::table)
+        .values(&statistics)
+        .get_results::<models::Statistics>(conn)?
+        .into_iter()
+        .map(Statistics::from)
+        .collect())
+}
+
 /// Get the JSON representation of the posted set of statistics in the
 /// database.
 #[post("/", format = "application/json", data = "<statistics>", rank = 1)]
@@ -49,23 +50,29 @@ pub fn post_statistic_json(
     post_statistic_data(&conn, statistics).map(Json)
 }
 
-// TODO FromDataSimple has to be implemented for CSV. To that end the csv
-// portion of the `rocket_contrib_local` crate must be fleshed out and made more
-// robust.
-// /// Get the CSV representation of the posted set of statistics in the database.
-// #[post("/", format = "text/csv", data = "<statistics>", rank = 2)]
-// pub fn post_statistic_csv(
-//     conn: CorgisDbConn,
-//     statistics: Csv<NewStatistics>,
-// ) -> Result<Csv<Statistics>, Error> {
-//     fn convertor(statistics: &Statistics) -> String {
-//         let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
-//         wtr.serialize(statistics).unwrap();
-//         String::from_utf8(wtr.into_inner().unwrap()).unwrap()
-//     };
+/// Get the CSV representation of the posted sets of statistics in the
+/// database. The body may contain one or many records, which are inserted
+/// together.
+#[post("/", format = "text/csv", data = "<statistics>", rank = 2)]
+pub fn post_statistic_csv(
+    conn: CorgisDbConn,
+    statistics: Result<Csv<Vec<NewStatistics>>, CsvError>,
+) -> Result<Result<Csv<Vec<Statistics>>, CsvError>, Error> {
+    fn convertor(statistics_set: &Vec<Statistics>) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        for statistics in statistics_set {
+            wtr.serialize(statistics).unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let statistics = match statistics {
+        Ok(statistics) => statistics.into_inner(),
+        Err(error) => return Ok(Err(error)),
+    };
 
-//     post_statistic_data(&conn, statistics).map(|data| Csv(data, convertor))
-// }
+    post_statistics_data(&conn, statistics).map(|data| Ok(Csv(data, convertor)))
+}
 
 /// Get the HAL representation of the posted set of statistics in the database.
 #[post("/", format = "application/hal+json", data = "<statistics>", rank = 3)]
@@ -0,0 +1,278 @@
+//! A module defining the `post_statistics_batch` routes.
+
+use super::views::{BatchStatus, NewStatistics, StatisticsBatch, StatisticsBatchRecord};
+
+use crate::routes::parameters::ParameterError;
+use crate::CorgisDbConn;
+use diesel::{
+    prelude::*,
+    result::{DatabaseErrorKind, Error},
+};
+use rocket::{
+    http::{RawStr, Status},
+    post,
+    request::FromFormValue,
+    response::status::Custom,
+};
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::{Csv, CsvError};
+use rustic_hal::HalResource;
+
+/// The way in which the records of a batch are inserted.
+#[derive(Clone, Copy)]
+pub enum BatchMode {
+    /// All records are inserted in a single transaction, which is rolled back
+    /// at the first record that fails.
+    Atomic,
+
+    /// Each record is inserted on its own, the records that fail do not affect
+    /// the other records.
+    BestEffort,
+}
+
+impl<'v> FromFormValue<'v> for BatchMode {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "atomic" => Ok(BatchMode::Atomic),
+            "best_effort" => Ok(BatchMode::BestEffort),
+            _ => Err(ParameterError::new(
+                "mode",
+                format!(
+                    "unknown mode `{}`, expected atomic or best_effort",
+                    form_value
+                ),
+            )),
+        }
+    }
+}
+
+/// Check the `mode` parameter of a request, the records are inserted
+/// atomically if it is not given.
+fn check_parameters(
+    mode: Option<Result<BatchMode, ParameterError>>,
+) -> Result<BatchMode, ParameterError> {
+    Ok(mode.transpose()?.unwrap_or(BatchMode::Atomic))
+}
+
+/// Insert a single record of a batch and return its result.
+fn insert_record(
+    conn: &diesel::PgConnection,
+    row: usize,
+    new_statistics: &NewStatistics,
+) -> StatisticsBatchRecord {
+    use crate::database::models;
+
+    match crate::database::create_statistics(conn, models::NewStatistics::from(new_statistics)) {
+        Ok(statistics) => StatisticsBatchRecord {
+            row,
+            status: BatchStatus::Created,
+            id: Some(statistics.id),
+            error: None,
+        },
+        Err(error) => {
+            let status = match error {
+                Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                    BatchStatus::Conflict
+                }
+                Error::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+                    BatchStatus::Invalid
+                }
+                _ => BatchStatus::Failed,
+            };
+            StatisticsBatchRecord {
+                row,
+                status,
+                id: None,
+                error: Some(error.to_string()),
+            }
+        }
+    }
+}
+
+/// The status of the response to a `batch`, 422 Unprocessable Entity if it was
+/// rolled back and 200 OK otherwise. A batch inserted with best effort is never
+/// rolled back, whichever of its records fail.
+fn batch_status(batch: &StatisticsBatch) -> Status {
+    if batch.committed {
+        Status::Ok
+    } else {
+        Status::UnprocessableEntity
+    }
+}
+
+fn post_statistics_batch_data(
+    conn: &diesel::PgConnection,
+    statistics: Vec<NewStatistics>,
+    mode: BatchMode,
+) -> Result<StatisticsBatch, Error> {
+    let mut records = Vec::with_capacity(statistics.len());
+
+    let committed = match mode {
+        BatchMode::Atomic => {
+            let transaction = conn.transaction::<_, Error, _>(|| {
+                for (index, new_statistics) in statistics.iter().enumerate() {
+                    let record = insert_record(conn, index + 1, new_statistics);
+                    let status = record.status;
+                    records.push(record);
+                    if status != BatchStatus::Created {
+                        return Err(Error::RollbackTransaction);
+                    }
+                }
+                Ok(())
+            });
+
+            match transaction {
+                Ok(()) => true,
+                Err(Error::RollbackTransaction) => {
+                    // Nothing of the batch remains, so report the records that
+                    // were inserted as rolled back and the remainder as skipped.
+                    for record in records.iter_mut() {
+                        if record.status == BatchStatus::Created {
+                            record.status = BatchStatus::RolledBack;
+                            record.id = None;
+                        }
+                    }
+                    for row in records.len() + 1..=statistics.len() {
+                        records.push(StatisticsBatchRecord {
+                            row,
+                            status: BatchStatus::Skipped,
+                            id: None,
+                            error: None,
+                        });
+                    }
+                    false
+                }
+                Err(error) => return Err(error),
+            }
+        }
+        BatchMode::BestEffort => {
+            for (index, new_statistics) in statistics.iter().enumerate() {
+                records.push(insert_record(conn, index + 1, new_statistics));
+            }
+            true
+        }
+    };
+
+    let created = records
+        .iter()
+        .filter(|record| record.status == BatchStatus::Created)
+        .count();
+    let failed = records
+        .iter()
+        .filter(|record| match record.status {
+            BatchStatus::Conflict | BatchStatus::Invalid | BatchStatus::Failed => true,
+            _ => false,
+        })
+        .count();
+
+    Ok(StatisticsBatch {
+        committed,
+        created,
+        failed,
+        records,
+    })
+}
+
+/// Get the JSON representation of the results of inserting a batch of
+/// statistics into the database.
+#[post(
+    "/batch?<mode>",
+    format = "application/json",
+    data = "<statistics>",
+    rank = 1
+)]
+pub fn post_statistics_batch_json(
+    conn: CorgisDbConn,
+    mode: Option<Result<BatchMode, ParameterError>>,
+    statistics: Json<Vec<NewStatistics>>,
+) -> Result<Result<Custom<Json<StatisticsBatch>>, ParameterError>, Error> {
+    let mode = match check_parameters(mode) {
+        Ok(mode) => mode,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    post_statistics_batch_data(&conn, statistics.into_inner(), mode)
+        .map(|data| Ok(Custom(batch_status(&data), Json(data))))
+}
+
+/// Get the CSV representation of the results of inserting a batch of
+/// statistics into the database. Each row holds the result of a record of the
+/// batch.
+#[post("/batch?<mode>", format = "text/csv", data = "<statistics>", rank = 2)]
+pub fn post_statistics_batch_csv(
+    conn: CorgisDbConn,
+    mode: Option<Result<BatchMode, ParameterError>>,
+    statistics: Result<Csv<Vec<NewStatistics>>, CsvError>,
+) -> Result<Result<Result<Custom<Csv<StatisticsBatch>>, CsvError>, ParameterError>, Error> {
+    fn convertor(batch: &StatisticsBatch) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        for record in &batch.records {
+            wtr.serialize(record).unwrap();
+        }
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let mode = match check_parameters(mode) {
+        Ok(mode) => mode,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let statistics = match statistics {
+        Ok(statistics) => statistics.into_inner(),
+        Err(error) => return Ok(Ok(Err(error))),
+    };
+
+    post_statistics_batch_data(&conn, statistics, mode)
+        .map(|data| Ok(Ok(Custom(batch_status(&data), Csv(data, convertor)))))
+}
+
+/// Get the HAL representation of the results of inserting a batch of
+/// statistics into the database.
+#[post(
+    "/batch?<mode>",
+    format = "application/hal+json",
+    data = "<statistics>",
+    rank = 3
+)]
+pub fn post_statistics_batch_hal(
+    conn: CorgisDbConn,
+    mode: Option<Result<BatchMode, ParameterError>>,
+    statistics: Json<Vec<NewStatistics>>,
+) -> Result<Result<Custom<Json<HalResource>>, ParameterError>, Error> {
+    let mode = match check_parameters(mode) {
+        Ok(mode) => mode,
+        Err(error) => return Ok(Err(error)),
+    };
+
+    let mut data = post_statistics_batch_data(&conn, statistics.into_inner(), mode)?;
+    let status = batch_status(&data);
+    let records = std::mem::replace(&mut data.records, Vec::new())
+        .into_iter()
+        .map(|record| match record.id {
+            Some(id) => {
+                HalResource::new(&record).with_link("statistics", format!("/statistics/{}", id))
+            }
+            None => HalResource::new(&record),
+        })
+        .collect();
+
+    let result = HalResource::new(&data)
+        .with_link("self", "/statistics/batch")
+        .with_resources("records", records);
+
+    Ok(Ok(Custom(status, Json(result))))
+}
+
+/// Get the default representation of the results of inserting a batch of
+/// statistics into the data store. This is executed if the other routes are not
+/// matched.
+#[post("/batch?<mode>", data = "<statistics>", rank = 4)]
+pub fn post_statistics_batch_default(
+    conn: CorgisDbConn,
+    mode: Option<Result<BatchMode, ParameterError>>,
+    statistics: Json<Vec<NewStatistics>>,
+) -> Result<Result<Custom<Json<StatisticsBatch>>, ParameterError>, diesel::result::Error> {
+    post_statistics_batch_json(conn, mode, statistics)
+}
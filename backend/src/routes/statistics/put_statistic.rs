@@ -1 +1,110 @@
+//! A module defining the `put_statistic` routes.
 
+use super::views::NewStatistics;
+use super::views::Statistics;
+
+use crate::CorgisDbConn;
+use diesel::{prelude::*, result::Error};
+use rocket::put;
+use rocket_contrib::json::Json;
+use rocket_contrib_local::csv::{Csv, CsvError};
+use rustic_hal::HalResource;
+
+fn put_statistics_data(
+    conn: &diesel::PgConnection,
+    id: i64,
+    new_statistics: NewStatistics,
+) -> Result<Option<Statistics>, Error> {
+    use crate::database::models;
+    use crate::database::schema::statistics::dsl::statistics;
+
+    Ok(diesel::update(statistics.find(id))
+        .set(&models::NewStatistics::from(&new_statistics))
+        .get_result::<models::Statistics>(conn)
+        .optional()?
+        .map(Statistics::from))
+}
+
+/// Get the JSON representation of the replaced set of statistics in the
+/// database.
+#[put(
+    "/<statistics>",
+    format = "application/json",
+    data = "<new_statistics>",
+    rank = 1
+)]
+pub fn put_statistics_json(
+    conn: CorgisDbConn,
+    statistics: i64,
+    new_statistics: Json<NewStatistics>,
+) -> Result<Option<Json<Statistics>>, Error> {
+    Ok(put_statistics_data(&conn, statistics, new_statistics.into_inner())?.map(Json))
+}
+
+/// Get the CSV representation of the replaced set of statistics in the
+/// database.
+#[put(
+    "/<statistics>",
+    format = "text/csv",
+    data = "<new_statistics>",
+    rank = 2
+)]
+pub fn put_statistics_csv(
+    conn: CorgisDbConn,
+    statistics: i64,
+    new_statistics: Result<Csv<NewStatistics>, CsvError>,
+) -> Result<Result<Option<Csv<Statistics>>, CsvError>, Error> {
+    fn convertor(statistics: &Statistics) -> String {
+        let mut wtr = csv::WriterBuilder::default().from_writer(Vec::new());
+        wtr.serialize(statistics).unwrap();
+        String::from_utf8(wtr.into_inner().unwrap()).unwrap()
+    };
+
+    let new_statistics = match new_statistics {
+        Ok(new_statistics) => new_statistics.into_inner(),
+        Err(error) => return Ok(Err(error)),
+    };
+
+    Ok(Ok(
+        put_statistics_data(&conn, statistics, new_statistics)?.map(|data| Csv(data, convertor))
+    ))
+}
+
+/// Get the HAL representation of the replaced set of statistics in the
+/// database.
+#[put(
+    "/<statistics>",
+    format = "application/hal+json",
+    data = "<new_statistics>",
+    rank = 3
+)]
+pub fn put_statistics_hal(
+    conn: CorgisDbConn,
+    statistics: i64,
+    new_statistics: Json<NewStatistics>,
+) -> Result<Option<Json<HalResource>>, Error> {
+    let data = put_statistics_data(&conn, statistics, new_statistics.into_inner())?;
+    match data {
+        None => Ok(None),
+        Some(data) => {
+            let result = HalResource::new(&data)
+                .with_link("self", format!("/statistics/{}", data.id))
+                .with_link("delete", format!("/statistics/{}", data.id))
+                .with_link("patch", format!("/statistics/{}", data.id))
+                .with_link("put", format!("/statistics/{}", data.id));
+
+            Ok(Some(Json(result)))
+        }
+    }
+}
+
+/// Get the default representation of the replaced set of statistics in the
+/// data store. This is executed if the other routes are not matched.
+#[put("/<statistics>", data = "<new_statistics>", rank = 4)]
+pub fn put_statistics_default(
+    conn: CorgisDbConn,
+    statistics: i64,
+    new_statistics: Json<NewStatistics>,
+) -> Result<Option<Json<Statistics>>, diesel::result::Error> {
+    put_statistics_json(conn, statistics, new_statistics)
+}
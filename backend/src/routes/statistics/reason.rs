@@ -0,0 +1,130 @@
+//! A module providing the reasons for delays by which the delay statistics may
+//! be filtered.
+
+use crate::routes::parameters::ParameterError;
+use rocket::{http::RawStr, request::FromFormValue};
+use std::fmt;
+
+/// A reason for delays, as recorded in the statistics.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Reason {
+    /// Delays due to the carrier.
+    Carrier,
+
+    /// Delays due to late aircraft.
+    LateAircraft,
+
+    /// Delays due to the national aviation system.
+    NationalAviationSystem,
+
+    /// Delays due to security.
+    Security,
+
+    /// Delays due to the weather.
+    Weather,
+}
+
+impl Reason {
+    /// Every reason, in the order of the statistics.
+    pub const ALL: &'static [Reason] = &[
+        Reason::Carrier,
+        Reason::LateAircraft,
+        Reason::NationalAviationSystem,
+        Reason::Security,
+        Reason::Weather,
+    ];
+
+    /// The reasons specific to the carrier, that is those under its control.
+    pub const CARRIER_SPECIFIC: &'static [Reason] = &[Reason::Carrier, Reason::LateAircraft];
+
+    /// The reasons external to the carrier.
+    pub const EXTERNAL: &'static [Reason] = &[
+        Reason::NationalAviationSystem,
+        Reason::Security,
+        Reason::Weather,
+    ];
+
+    /// The name of the reason.
+    pub fn name(self) -> &'static str {
+        match self {
+            Reason::Carrier => "carrier",
+            Reason::LateAircraft => "late_aircraft",
+            Reason::NationalAviationSystem => "national_aviation_system",
+            Reason::Security => "security",
+            Reason::Weather => "weather",
+        }
+    }
+}
+
+/// The reasons for delays as given by the `reason` parameter, a comma separated
+/// list of reasons, where `carrier_specific` stands for `carrier` and
+/// `late_aircraft`, and `external` for `national_aviation_system`, `security`
+/// and `weather`, e.g. `reason=carrier_specific,weather`.
+#[derive(Clone, Debug)]
+pub struct Reasons(Vec<Reason>);
+
+impl<'v> FromFormValue<'v> for Reasons {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let value = form_value
+            .url_decode()
+            .map_err(|_| ParameterError::new("reason", "not valid UTF-8"))?;
+
+        let mut reasons = Vec::new();
+        for name in value.split(',').map(str::trim) {
+            match name {
+                "carrier_specific" => reasons.extend_from_slice(Reason::CARRIER_SPECIFIC),
+                "external" => reasons.extend_from_slice(Reason::EXTERNAL),
+                _ => match Reason::ALL.iter().find(|reason| reason.name() == name) {
+                    Some(reason) => reasons.push(*reason),
+                    None => {
+                        return Err(ParameterError::new(
+                            "reason",
+                            format!(
+                                "unknown reason `{}`, expected one of carrier, late_aircraft, \
+                                 national_aviation_system, security, weather, carrier_specific \
+                                 or external",
+                                name
+                            ),
+                        ));
+                    }
+                },
+            }
+        }
+
+        Ok(Reasons(
+            Reason::ALL
+                .iter()
+                .cloned()
+                .filter(|reason| reasons.contains(reason))
+                .collect(),
+        ))
+    }
+}
+
+impl fmt::Display for Reasons {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = self.0.iter().map(|reason| reason.name()).collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+
+impl Default for Reasons {
+    /// Every reason.
+    fn default() -> Self {
+        Reasons(Reason::ALL.to_vec())
+    }
+}
+
+impl Reasons {
+    /// Whether the `reason` is one of the reasons.
+    pub fn contains(&self, reason: Reason) -> bool {
+        self.0.contains(&reason)
+    }
+
+    /// Format the reasons as a query parameter of a URI.
+    pub fn to_query(&self) -> String {
+        format!("reason={}", self)
+    }
+}
@@ -0,0 +1,117 @@
+//! A module providing the sorting of the statistics collections.
+
+use super::columns::{column_names, Column};
+use super::filter::StatisticsQuery;
+
+use crate::routes::pagination::Cursor;
+use crate::routes::parameters::ParameterError;
+use diesel::prelude::*;
+use rocket::{http::RawStr, request::FromFormValue};
+use std::fmt;
+
+/// The order of the statistics as given by the `sort` parameter, a comma
+/// separated list of columns where a leading `-` sorts the column in descending
+/// order, e.g. `sort=time,-flights_delayed`.
+#[derive(Clone, Debug)]
+pub struct Sort(Vec<(Column, bool)>);
+
+impl<'v> FromFormValue<'v> for Sort {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let value = form_value
+            .url_decode()
+            .map_err(|_| ParameterError::new("sort", "not valid UTF-8"))?;
+
+        let mut columns = Vec::new();
+        for key in value.split(',').map(str::trim) {
+            let (name, descending) = if key.starts_with('-') {
+                (&key[1..], true)
+            } else {
+                (key, false)
+            };
+
+            let column = Column::from_name(name).ok_or_else(|| {
+                ParameterError::new(
+                    "sort",
+                    format!(
+                        "unknown column `{}`, expected one of {}",
+                        name,
+                        column_names()
+                    ),
+                )
+            })?;
+            if columns.iter().any(|(other, _)| *other == column) {
+                return Err(ParameterError::new(
+                    "sort",
+                    format!("column `{}` is given more than once", name),
+                ));
+            }
+            columns.push((column, descending));
+        }
+
+        Ok(Sort(columns))
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keys: Vec<String> = self
+            .0
+            .iter()
+            .map(|(column, descending)| {
+                format!("{}{}", if *descending { "-" } else { "" }, column.name())
+            })
+            .collect();
+        write!(f, "{}", keys.join(","))
+    }
+}
+
+impl Sort {
+    /// Order the `query` by the columns in turn. The id is used to break any
+    /// remaining ties, so the order is stable between pages.
+    pub fn order(&self, query: StatisticsQuery) -> StatisticsQuery {
+        let query = self.0.iter().fold(query, |query, (column, descending)| {
+            column.order(query, *descending)
+        });
+
+        if self.0.iter().any(|(column, _)| *column == Column::Id) {
+            query
+        } else {
+            query.then_order_by(crate::database::schema::statistics::id.asc())
+        }
+    }
+
+    /// Format the order as a query parameter of a URI.
+    pub fn to_query(&self) -> String {
+        format!("sort={}", self)
+    }
+
+    /// Whether the order is the chronological order, which is the only order
+    /// in which a cursor is a position.
+    pub fn is_chronological(&self) -> bool {
+        match self.0.as_slice() {
+            [(Column::Time, false)] | [(Column::Time, false), (Column::Id, false)] => true,
+            _ => false,
+        }
+    }
+}
+
+/// Check the `sort` parameter of a request, a cursor can only be used in the
+/// chronological order.
+pub fn check_sort(
+    sort: Option<Result<Sort, ParameterError>>,
+    cursor: Option<Cursor>,
+) -> Result<Option<Sort>, ParameterError> {
+    match sort {
+        None => Ok(None),
+        Some(Err(error)) => Err(error),
+        Some(Ok(ref sort)) if cursor.is_some() && !sort.is_chronological() => {
+            Err(ParameterError::new(
+                "cursor",
+                "a cursor can only be used in the chronological order",
+            ))
+        }
+        Some(Ok(sort)) => Ok(Some(sort)),
+    }
+}
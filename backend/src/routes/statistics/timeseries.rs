@@ -0,0 +1,392 @@
+//! A module providing the monthly time series of the statistics.
+
+use super::columns::{column_names, Column};
+use super::filter::StatisticsQuery;
+use super::views::{TimeSeries, TimeSeriesPoint};
+
+use crate::database::schema::statistics;
+use crate::routes::parameters::{ParameterError, YearMonth};
+use chrono::NaiveDate;
+use diesel::{dsl::sql, prelude::*, result::Error, sql_types::BigInt};
+use rocket::{http::RawStr, request::FromFormValue};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// The column of the statistics summed for each month of the series as given
+/// by the `metric` parameter, e.g. `metric=flights_delayed`.
+#[derive(Clone, Copy, Debug)]
+pub struct SeriesMetric(Column);
+
+impl<'v> FromFormValue<'v> for SeriesMetric {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        let value = form_value
+            .url_decode()
+            .map_err(|_| ParameterError::new("metric", "not valid UTF-8"))?;
+        let name = value.trim();
+
+        match Column::from_name(name) {
+            Some(column) if column.is_aggregable() => Ok(SeriesMetric(column)),
+            Some(_) => Err(ParameterError::new(
+                "metric",
+                format!("column `{}` can not be summed", name),
+            )),
+            None => Err(ParameterError::new(
+                "metric",
+                format!(
+                    "unknown column `{}`, expected one of {}",
+                    name,
+                    column_names()
+                ),
+            )),
+        }
+    }
+}
+
+impl Default for SeriesMetric {
+    /// The total number of flights.
+    fn default() -> Self {
+        SeriesMetric(Column::FlightsTotal)
+    }
+}
+
+impl fmt::Display for SeriesMetric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0.name())
+    }
+}
+
+/// How the months without any statistics are represented, as given by the
+/// `fill` parameter, either `null` or `zero`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Fill {
+    /// The value of a missing month is null.
+    Null,
+
+    /// The value of a missing month is zero.
+    Zero,
+}
+
+impl Default for Fill {
+    /// A missing month is null.
+    fn default() -> Self {
+        Fill::Null
+    }
+}
+
+impl<'v> FromFormValue<'v> for Fill {
+    type Error = ParameterError;
+
+    fn from_form_value(form_value: &'v RawStr) -> Result<Self, Self::Error> {
+        match form_value.as_str() {
+            "null" => Ok(Fill::Null),
+            "zero" => Ok(Fill::Zero),
+            _ => Err(ParameterError::new(
+                "fill",
+                format!("unknown fill `{}`, expected null or zero", form_value),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Fill {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fill::Null => write!(f, "null"),
+            Fill::Zero => write!(f, "zero"),
+        }
+    }
+}
+
+/// The parameters of a time series, taken from the query parameters of a
+/// request.
+pub struct SeriesParameters {
+    /// The column summed for each month.
+    pub metric: SeriesMetric,
+
+    /// How the missing months are represented.
+    pub fill: Fill,
+
+    /// The number of months of the rolling average, if requested.
+    pub rolling: Option<usize>,
+
+    /// Whether the difference with the same month of the previous year is
+    /// requested.
+    pub year_over_year: bool,
+}
+
+impl SeriesParameters {
+    /// Check the `metric`, `fill` and `rolling` parameters of a request, the
+    /// rolling average spans at least one month.
+    pub fn check(
+        metric: Option<Result<SeriesMetric, ParameterError>>,
+        fill: Option<Result<Fill, ParameterError>>,
+        rolling: Option<usize>,
+        yoy: Option<bool>,
+    ) -> Result<Self, ParameterError> {
+        if rolling == Some(0) {
+            return Err(ParameterError::new(
+                "rolling",
+                "the rolling average spans at least one month",
+            ));
+        }
+
+        Ok(SeriesParameters {
+            metric: metric.transpose()?.unwrap_or_default(),
+            fill: fill.transpose()?.unwrap_or_default(),
+            rolling,
+            year_over_year: yoy.unwrap_or(false),
+        })
+    }
+
+    /// Format the parameters as the query parameters of a URI.
+    pub fn to_query(&self) -> String {
+        let mut parameters = vec![
+            format!("metric={}", self.metric),
+            format!("fill={}", self.fill),
+        ];
+        if let Some(rolling) = self.rolling {
+            parameters.push(format!("rolling={}", rolling));
+        }
+        if self.year_over_year {
+            parameters.push(String::from("yoy=true"));
+        }
+        parameters.join("&")
+    }
+}
+
+/// Build the monthly time series of the statistics matched by the `query`, the
+/// metric being summed by the database for each month. The series holds every
+/// month from the first to the last month with statistics, the months in
+/// between without any statistics are marked as missing.
+pub fn load_timeseries(
+    conn: &diesel::PgConnection,
+    query: Option<StatisticsQuery>,
+    parameters: &SeriesParameters,
+) -> Result<TimeSeries, Error> {
+    let rows = match query {
+        Some(query) => query
+            .select((
+                statistics::time,
+                sql::<BigInt>(&format!(
+                    "CAST(SUM(statistics.{}) AS INT8)",
+                    parameters.metric
+                )),
+            ))
+            .group_by(statistics::time)
+            .order(statistics::time)
+            .load::<(NaiveDate, i64)>(conn)?,
+        None => Vec::new(),
+    };
+
+    let mut values = BTreeMap::new();
+    for (time, value) in rows {
+        *values.entry(YearMonth::from(time)).or_insert(0) += value;
+    }
+
+    Ok(TimeSeries {
+        metric: parameters.metric.to_string(),
+        fill: parameters.fill.to_string(),
+        points: series_points(&values, parameters),
+    })
+}
+
+/// Build the points of the series of the monthly `values`, from the first to
+/// the last month with a value. The rolling average and the year over year
+/// difference are computed from the values present, so a missing month is not
+/// counted as zero even if it is filled with zero.
+fn series_points(
+    values: &BTreeMap<YearMonth, i64>,
+    parameters: &SeriesParameters,
+) -> Vec<TimeSeriesPoint> {
+    let mut observed = Vec::new();
+    if let (Some(first), Some(last)) = (
+        values.keys().next().cloned(),
+        values.keys().next_back().cloned(),
+    ) {
+        let mut month = Some(first);
+        while let Some(current) = month.filter(|current| *current <= last) {
+            observed.push((current, values.get(&current).cloned()));
+            month = current.next();
+        }
+    }
+
+    let rolling_average = |index: usize| -> Option<Option<f64>> {
+        let rolling = parameters.rolling?;
+        // Only complete windows are averaged, over the values present.
+        if index + 1 < rolling {
+            return Some(None);
+        }
+        let present: Vec<i64> = observed[index + 1 - rolling..=index]
+            .iter()
+            .filter_map(|(_, value)| *value)
+            .collect();
+        if present.is_empty() {
+            Some(None)
+        } else {
+            Some(Some(
+                present.iter().sum::<i64>() as f64 / present.len() as f64,
+            ))
+        }
+    };
+
+    let year_over_year_delta = |index: usize| -> Option<Option<i64>> {
+        if !parameters.year_over_year {
+            return None;
+        }
+        if index < 12 {
+            return Some(None);
+        }
+        match (observed[index - 12].1, observed[index].1) {
+            (Some(previous), Some(current)) => Some(Some(current - previous)),
+            _ => Some(None),
+        }
+    };
+
+    observed
+        .iter()
+        .enumerate()
+        .map(|(index, (month, value))| TimeSeriesPoint {
+            month: *month,
+            value: match (value, parameters.fill) {
+                (None, Fill::Zero) => Some(0),
+                _ => *value,
+            },
+            missing: value.is_none(),
+            rolling_average: rolling_average(index),
+            year_over_year_delta: year_over_year_delta(index),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The parameters of a series filled with `fill`.
+    fn parameters(fill: Fill, rolling: Option<usize>, year_over_year: bool) -> SeriesParameters {
+        SeriesParameters {
+            metric: Default::default(),
+            fill,
+            rolling,
+            year_over_year,
+        }
+    }
+
+    /// The monthly values, starting in January 2010, `None` for a month
+    /// without any statistics.
+    fn values(values: &[Option<i64>]) -> BTreeMap<YearMonth, i64> {
+        values
+            .iter()
+            .enumerate()
+            .filter_map(|(index, value)| {
+                value.map(|value| {
+                    (
+                        YearMonth {
+                            year: 2010 + (index / 12) as i32,
+                            month: (index % 12) as u32 + 1,
+                        },
+                        value,
+                    )
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn gaps_are_null_or_zero() {
+        let values = values(&[Some(4), None, Some(6)]);
+
+        let points = series_points(&values, &parameters(Fill::Null, None, false));
+        let filled: Vec<Option<i64>> = points.iter().map(|point| point.value).collect();
+        assert_eq!(filled, vec![Some(4), None, Some(6)]);
+
+        let points = series_points(&values, &parameters(Fill::Zero, None, false));
+        let filled: Vec<Option<i64>> = points.iter().map(|point| point.value).collect();
+        assert_eq!(filled, vec![Some(4), Some(0), Some(6)]);
+        let missing: Vec<bool> = points.iter().map(|point| point.missing).collect();
+        assert_eq!(missing, vec![false, true, false]);
+    }
+
+    #[test]
+    fn series_spans_the_first_to_the_last_month_across_years() {
+        let values = values(&[
+            Some(1),
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            None,
+            Some(2),
+        ]);
+        let points = series_points(&values, &parameters(Fill::Null, None, false));
+        assert_eq!(points.len(), 13);
+        assert_eq!(
+            points[0].month,
+            YearMonth {
+                year: 2010,
+                month: 1
+            }
+        );
+        assert_eq!(
+            points[12].month,
+            YearMonth {
+                year: 2011,
+                month: 1
+            }
+        );
+    }
+
+    #[test]
+    fn rolling_average_skips_gaps_even_if_filled_with_zero() {
+        let values = values(&[Some(4), None, Some(6), Some(8)]);
+        for fill in &[Fill::Null, Fill::Zero] {
+            let points = series_points(&values, &parameters(*fill, Some(2), false));
+            let averages: Vec<Option<Option<f64>>> =
+                points.iter().map(|point| point.rolling_average).collect();
+            assert_eq!(
+                averages,
+                vec![
+                    Some(None),
+                    Some(Some(4.0)),
+                    Some(Some(6.0)),
+                    Some(Some(7.0))
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn rolling_average_is_absent_unless_requested() {
+        let points = series_points(
+            &values(&[Some(1), Some(2)]),
+            &parameters(Fill::Null, None, false),
+        );
+        assert!(points.iter().all(|point| point.rolling_average.is_none()));
+    }
+
+    #[test]
+    fn year_over_year_delta_skips_gaps_even_if_filled_with_zero() {
+        let mut months = vec![None; 14];
+        months[0] = Some(10);
+        months[12] = Some(15);
+        months[13] = Some(25);
+        let values = values(&months);
+
+        for fill in &[Fill::Null, Fill::Zero] {
+            let points = series_points(&values, &parameters(*fill, None, true));
+            assert_eq!(points.len(), 14);
+            assert_eq!(points[0].year_over_year_delta, Some(None));
+            assert_eq!(points[12].year_over_year_delta, Some(Some(5)));
+            // The previous year of February 2011 is missing, not zero.
+            assert_eq!(points[13].year_over_year_delta, Some(None));
+        }
+    }
+}
@@ -1,7 +1,10 @@
 //! A module containing the statistics structures returned by the API.
 
+use super::reason::{Reason, Reasons};
+
+use rocket_contrib_local::csv::CsvRecord;
 use serde::Deserialize;
-use serde::Serialize;
+use serde::{ser::SerializeMap, Serialize, Serializer};
 
 /// A set of statistics as returned by the API.
 #[derive(Serialize)]
@@ -96,6 +99,152 @@ impl From<crate::database::models::Statistics> for Statistics {
     }
 }
 
+/// A set of statistics as returned by the API, of which only the requested
+/// fields are represented.
+pub struct SparseStatistics {
+    /// The id for the set of statistics, which is only represented if it is
+    /// one of the fields.
+    pub id: i64,
+
+    /// The time, which is only represented if it is one of the fields.
+    pub time: chrono::NaiveDate,
+
+    /// The names and values of the fields, in the order requested.
+    pub fields: Vec<(&'static str, Option<FieldValue>)>,
+}
+
+impl Serialize for SparseStatistics {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for (name, value) in &self.fields {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+/// The value of a field of a set of statistics.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum FieldValue {
+    /// A count or a number of minutes.
+    Integer(i64),
+
+    /// A date.
+    Date(chrono::NaiveDate),
+}
+
+impl From<i64> for FieldValue {
+    fn from(value: i64) -> Self {
+        FieldValue::Integer(value)
+    }
+}
+
+impl From<chrono::NaiveDate> for FieldValue {
+    fn from(value: chrono::NaiveDate) -> Self {
+        FieldValue::Date(value)
+    }
+}
+
+impl std::fmt::Display for FieldValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FieldValue::Integer(value) => write!(f, "{}", value),
+            FieldValue::Date(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// A group of statistics with its metrics as returned by the API.
+pub struct AggregateStatistics {
+    /// The id of the carrier of the group, if grouped by carrier.
+    pub carrier_id: Option<i64>,
+
+    /// The id of the airport of the group, if grouped by airport.
+    pub airport_id: Option<i64>,
+
+    /// The year of the group, if grouped by year.
+    pub year: Option<i32>,
+
+    /// The month of the group, if grouped by month.
+    pub month: Option<i32>,
+
+    /// The names and values of the keys of the group followed by those of the
+    /// metrics, in the order requested.
+    pub fields: Vec<(String, Option<AggregateValue>)>,
+}
+
+impl Serialize for AggregateStatistics {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.fields.len()))?;
+        for (name, value) in &self.fields {
+            map.serialize_entry(name, value)?;
+        }
+        map.end()
+    }
+}
+
+/// The value of a key or metric of a group of statistics.
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum AggregateValue {
+    /// A whole number.
+    Integer(i64),
+
+    /// A number which need not be whole, such as a mean.
+    Real(f64),
+
+    /// A code or a name.
+    Text(String),
+}
+
+impl std::fmt::Display for AggregateValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            AggregateValue::Integer(value) => write!(f, "{}", value),
+            AggregateValue::Real(value) => write!(f, "{}", value),
+            AggregateValue::Text(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+/// A monthly time series of the statistics as returned by the API.
+#[derive(Serialize)]
+pub struct TimeSeries {
+    /// The column summed for each month.
+    pub metric: String,
+
+    /// How the missing months are represented, either `null` or `zero`.
+    pub fill: String,
+
+    /// The points of the series, one for each month.
+    pub points: Vec<TimeSeriesPoint>,
+}
+
+/// A month of a time series.
+#[derive(Serialize)]
+pub struct TimeSeriesPoint {
+    /// The month, formatted as `YYYY-MM`.
+    pub month: crate::routes::parameters::YearMonth,
+
+    /// The sum of the metric over the statistics of the month. A missing month
+    /// is null or zero, depending on the fill.
+    pub value: Option<i64>,
+
+    /// Whether there are no statistics for the month.
+    pub missing: bool,
+
+    /// The average of the values present in the rolling window ending with
+    /// the month, if requested. It is null until the window is complete.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rolling_average: Option<Option<f64>>,
+
+    /// The difference with the value of the same month of the previous year,
+    /// if requested. It is null if either value is missing.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year_over_year_delta: Option<Option<i64>>,
+}
+
 /// A set of statistics specifically on flights as returned by the API.
 #[derive(Serialize)]
 pub struct StatisticsFlights {
@@ -169,7 +318,7 @@ pub struct StatisticsMinutesDelayed {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub minutes_delayed_weather: Option<i64>,
 
-    /// The total minutes delayed.
+    /// The total minutes delayed due to the reasons represented.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub minutes_delayed_total: Option<i64>,
 
@@ -183,18 +332,204 @@ pub struct StatisticsMinutesDelayed {
     pub airport_id: i64,
 }
 
-impl From<crate::database::models::Statistics> for StatisticsMinutesDelayed {
-    fn from(source: crate::database::models::Statistics) -> Self {
+impl StatisticsMinutesDelayed {
+    /// Represent the minutes delayed of the `source` due to the `reasons` only,
+    /// the total being that of those reasons.
+    pub fn new(source: crate::database::models::Statistics, reasons: &Reasons) -> Self {
+        let minutes = |reason, minutes| {
+            if reasons.contains(reason) {
+                Some(minutes)
+            } else {
+                None
+            }
+        };
+
+        let minutes_delayed = [
+            minutes(Reason::Carrier, source.minutes_delayed_carrier),
+            minutes(Reason::LateAircraft, source.minutes_delayed_late_aircraft),
+            minutes(
+                Reason::NationalAviationSystem,
+                source.minutes_delayed_national_aviation_system,
+            ),
+            minutes(Reason::Security, source.minutes_delayed_security),
+            minutes(Reason::Weather, source.minutes_delayed_weather),
+        ];
+
         Self {
             id: source.id,
-            minutes_delayed_carrier: Some(source.minutes_delayed_carrier),
-            minutes_delayed_late_aircraft: Some(source.minutes_delayed_late_aircraft),
-            minutes_delayed_national_aviation_system: Some(
+            minutes_delayed_carrier: minutes_delayed[0],
+            minutes_delayed_late_aircraft: minutes_delayed[1],
+            minutes_delayed_national_aviation_system: minutes_delayed[2],
+            minutes_delayed_security: minutes_delayed[3],
+            minutes_delayed_weather: minutes_delayed[4],
+            minutes_delayed_total: Some(
+                minutes_delayed.iter().filter_map(|minutes| *minutes).sum(),
+            ),
+            time: source.time,
+            carrier_id: source.carrier_id,
+            airport_id: source.airport_id,
+        }
+    }
+}
+
+/// A set of statistics on the number of delays as returned by the API.
+#[derive(Serialize)]
+pub struct StatisticsDelaysCount {
+    /// The id for a set of statistics.
+    pub id: i64,
+
+    /// The number of delays due to the carrier.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_of_delays_carrier: Option<i64>,
+
+    /// The number of delays due to late aircraft.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_of_delays_late_aircraft: Option<i64>,
+
+    /// The number of delays due to the national aviation system.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_of_delays_national_aviation_system: Option<i64>,
+
+    /// The number of delays due to security.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_of_delays_security: Option<i64>,
+
+    /// The number of delays due to the weather.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub number_of_delays_weather: Option<i64>,
+
+    /// The total number of delays due to the reasons represented.
+    pub number_of_delays_total: i64,
+
+    /// The average minutes per delay due to the carrier, if requested. It is
+    /// null if there are no such delays.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_minutes_per_delay_carrier: Option<Option<f64>>,
+
+    /// The average minutes per delay due to late aircraft, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_minutes_per_delay_late_aircraft: Option<Option<f64>>,
+
+    /// The average minutes per delay due to the national aviation system, if
+    /// requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_minutes_per_delay_national_aviation_system: Option<Option<f64>>,
+
+    /// The average minutes per delay due to security, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_minutes_per_delay_security: Option<Option<f64>>,
+
+    /// The average minutes per delay due to the weather, if requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_minutes_per_delay_weather: Option<Option<f64>>,
+
+    /// The average minutes per delay due to the reasons represented, if
+    /// requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_minutes_per_delay_total: Option<Option<f64>>,
+
+    /// The time.
+    pub time: chrono::NaiveDate,
+
+    /// The id of the [carrier](Carrier).
+    pub carrier_id: i64,
+
+    /// The id of the [airport](Airport).
+    pub airport_id: i64,
+}
+
+impl StatisticsDelaysCount {
+    /// Represent the number of delays of the `source` due to the `reasons`
+    /// only, the total being that of those reasons. If `average` is set the
+    /// average minutes per delay is included for each of the reasons.
+    pub fn new(
+        source: crate::database::models::Statistics,
+        reasons: &Reasons,
+        average: bool,
+    ) -> Self {
+        let delays = [
+            (
+                Reason::Carrier,
+                source.number_of_delays_carrier,
+                source.minutes_delayed_carrier,
+            ),
+            (
+                Reason::LateAircraft,
+                source.number_of_delays_late_aircraft,
+                source.minutes_delayed_late_aircraft,
+            ),
+            (
+                Reason::NationalAviationSystem,
+                source.number_of_delays_national_aviation_system,
                 source.minutes_delayed_national_aviation_system,
             ),
-            minutes_delayed_security: Some(source.minutes_delayed_security),
-            minutes_delayed_weather: Some(source.minutes_delayed_weather),
-            minutes_delayed_total: Some(source.minutes_delayed_total),
+            (
+                Reason::Security,
+                source.number_of_delays_security,
+                source.minutes_delayed_security,
+            ),
+            (
+                Reason::Weather,
+                source.number_of_delays_weather,
+                source.minutes_delayed_weather,
+            ),
+        ];
+
+        let number_of_delays = |index: usize| {
+            let (reason, number, _) = delays[index];
+            if reasons.contains(reason) {
+                Some(number)
+            } else {
+                None
+            }
+        };
+        let average_minutes = |number: i64, minutes: i64| {
+            if number > 0 {
+                Some(minutes as f64 / number as f64)
+            } else {
+                None
+            }
+        };
+        let average_minutes_per_delay = |index: usize| {
+            let (reason, number, minutes) = delays[index];
+            if average && reasons.contains(reason) {
+                Some(average_minutes(number, minutes))
+            } else {
+                None
+            }
+        };
+
+        let (number_of_delays_total, minutes_delayed_total) = delays
+            .iter()
+            .filter(|(reason, _, _)| reasons.contains(*reason))
+            .fold(
+                (0, 0),
+                |(total_number, total_minutes), (_, number, minutes)| {
+                    (total_number + number, total_minutes + minutes)
+                },
+            );
+
+        Self {
+            id: source.id,
+            number_of_delays_carrier: number_of_delays(0),
+            number_of_delays_late_aircraft: number_of_delays(1),
+            number_of_delays_national_aviation_system: number_of_delays(2),
+            number_of_delays_security: number_of_delays(3),
+            number_of_delays_weather: number_of_delays(4),
+            number_of_delays_total,
+            average_minutes_per_delay_carrier: average_minutes_per_delay(0),
+            average_minutes_per_delay_late_aircraft: average_minutes_per_delay(1),
+            average_minutes_per_delay_national_aviation_system: average_minutes_per_delay(2),
+            average_minutes_per_delay_security: average_minutes_per_delay(3),
+            average_minutes_per_delay_weather: average_minutes_per_delay(4),
+            average_minutes_per_delay_total: if average {
+                Some(average_minutes(
+                    number_of_delays_total,
+                    minutes_delayed_total,
+                ))
+            } else {
+                None
+            },
             time: source.time,
             carrier_id: source.carrier_id,
             airport_id: source.airport_id,
@@ -202,10 +537,99 @@ impl From<crate::database::models::Statistics> for StatisticsMinutesDelayed {
     }
 }
 
+/// The descriptive statistics of a set of values as returned by the API. When
+/// the set is empty only the `count` is known.
+#[derive(Serialize)]
+pub struct DescriptiveStatistics {
+    /// The number of values.
+    pub count: usize,
+
+    /// The arithmetic mean of the values.
+    pub mean: Option<f64>,
+
+    /// The median of the values.
+    pub median: Option<f64>,
+
+    /// The sample standard deviation of the values. This requires at least 2
+    /// values.
+    pub standard_deviation: Option<f64>,
+
+    /// The smallest value.
+    pub min: Option<i64>,
+
+    /// The largest value.
+    pub max: Option<i64>,
+}
+
+impl From<Vec<i64>> for DescriptiveStatistics {
+    fn from(mut source: Vec<i64>) -> Self {
+        source.sort();
+
+        let count = source.len();
+        if count == 0 {
+            return Self {
+                count,
+                mean: None,
+                median: None,
+                standard_deviation: None,
+                min: None,
+                max: None,
+            };
+        }
+
+        let mean = source.iter().sum::<i64>() as f64 / count as f64;
+        let median = if count % 2 == 0 {
+            (source[count / 2 - 1] + source[count / 2]) as f64 / 2.
+        } else {
+            source[count / 2] as f64
+        };
+        let standard_deviation = if count > 1 {
+            let squares: f64 = source
+                .iter()
+                .map(|&value| (value as f64 - mean).powi(2))
+                .sum();
+            Some((squares / (count - 1) as f64).sqrt())
+        } else {
+            None
+        };
+
+        Self {
+            count,
+            mean: Some(mean),
+            median: Some(median),
+            standard_deviation,
+            min: source.first().cloned(),
+            max: source.last().cloned(),
+        }
+    }
+}
+
+/// The descriptive statistics of the carrier-specific delays between 2 airports
+/// as returned by the API.
+#[derive(Serialize)]
+pub struct ConnectionStatistics {
+    /// The id of the first [airport](Airport).
+    pub airport1: i64,
+
+    /// The id of the second [airport](Airport).
+    pub airport2: i64,
+
+    /// The ids of the [carriers](Carrier) the statistics are restricted to, if
+    /// any. A carrier code identifies every carrier that operated under it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub carriers: Option<Vec<i64>>,
+
+    /// The descriptive statistics of the minutes delayed due to the carrier.
+    pub minutes_delayed_carrier: DescriptiveStatistics,
+
+    /// The descriptive statistics of the minutes delayed due to late aircraft.
+    pub minutes_delayed_late_aircraft: DescriptiveStatistics,
+}
+
 /// A set of statistics with all the information needed for insertion. In this
 /// instance the `id` for the statistics is generated upon insertion, and the
 /// `flights_total` and `minutes_delayed_total` are derived values.
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 pub struct NewStatistics {
     /// See [Statistics::flights_cancelled].
     pub flights_cancelled: i64,
@@ -259,28 +683,164 @@ pub struct NewStatistics {
     pub airport_id: i64,
 }
 
-// impl<'a> Into<crate::database::models::NewStatistics<'a>> for NewStatistics {
-//     fn into(self) -> crate::database::models::NewStatistics<'a> {
-//         crate::database::models::NewStatistics {
-//             flights_cancelled: &self.flights_cancelled,
-//             flights_delayed: &self.flights_delayed,
-//             flights_diverted: &self.flights_diverted,
-//             flights_on_time: &self.flights_on_time,
-//             minutes_delayed_carrier: &self.minutes_delayed_carrier,
-//             minutes_delayed_late_aircraft: &self.minutes_delayed_late_aircraft,
-//             minutes_delayed_national_aviation_system: &self
-//                 .minutes_delayed_national_aviation_system,
-//             minutes_delayed_security: &self.minutes_delayed_security,
-//             minutes_delayed_weather: &self.minutes_delayed_weather,
-//             number_of_delays_carrier: &self.number_of_delays_carrier,
-//             number_of_delays_late_aircraft: &self.number_of_delays_late_aircraft,
-//             number_of_delays_national_aviation_system: &self
-//                 .number_of_delays_national_aviation_system,
-//             number_of_delays_security: &self.number_of_delays_security,
-//             number_of_delays_weather: &self.number_of_delays_weather,
-//             time: &self.time,
-//             carrier_id: &self.carrier_id,
-//             airport_id: &self.airport_id,
-//         }
-//     }
-// }
+impl CsvRecord for NewStatistics {}
+
+impl<'a> From<&'a NewStatistics> for crate::database::models::NewStatistics<'a> {
+    fn from(source: &'a NewStatistics) -> Self {
+        Self {
+            flights_cancelled: &source.flights_cancelled,
+            flights_delayed: &source.flights_delayed,
+            flights_diverted: &source.flights_diverted,
+            flights_on_time: &source.flights_on_time,
+            minutes_delayed_carrier: &source.minutes_delayed_carrier,
+            minutes_delayed_late_aircraft: &source.minutes_delayed_late_aircraft,
+            minutes_delayed_national_aviation_system: &source
+                .minutes_delayed_national_aviation_system,
+            minutes_delayed_security: &source.minutes_delayed_security,
+            minutes_delayed_weather: &source.minutes_delayed_weather,
+            number_of_delays_carrier: &source.number_of_delays_carrier,
+            number_of_delays_late_aircraft: &source.number_of_delays_late_aircraft,
+            number_of_delays_national_aviation_system: &source
+                .number_of_delays_national_aviation_system,
+            number_of_delays_security: &source.number_of_delays_security,
+            number_of_delays_weather: &source.number_of_delays_weather,
+            time: &source.time,
+            carrier_id: &source.carrier_id,
+            airport_id: &source.airport_id,
+        }
+    }
+}
+
+/// A set of statistics with the information needed for a partial update. Only
+/// the statistics which are present are changed, the `flights_total` and
+/// `minutes_delayed_total` are derived values.
+#[derive(Deserialize, Serialize)]
+pub struct UpdateStatistics {
+    /// See [Statistics::flights_cancelled].
+    pub flights_cancelled: Option<i64>,
+
+    /// See [Statistics::flights_delayed].
+    pub flights_delayed: Option<i64>,
+
+    /// See [Statistics::flights_diverted].
+    pub flights_diverted: Option<i64>,
+
+    /// See [Statistics::flights_on_time].
+    pub flights_on_time: Option<i64>,
+
+    /// See [Statistics::minutes_delayed_carrier].
+    pub minutes_delayed_carrier: Option<i64>,
+
+    /// See [Statistics::minutes_delayed_late_aircraft].
+    pub minutes_delayed_late_aircraft: Option<i64>,
+
+    /// See [Statistics::minutes_delayed_national_aviation_system].
+    pub minutes_delayed_national_aviation_system: Option<i64>,
+
+    /// See [Statistics::minutes_delayed_security].
+    pub minutes_delayed_security: Option<i64>,
+
+    /// See [Statistics::minutes_delayed_weather].
+    pub minutes_delayed_weather: Option<i64>,
+
+    /// See [Statistics::number_of_delays_carrier].
+    pub number_of_delays_carrier: Option<i64>,
+
+    /// See [Statistics::number_of_delays_late_aircraft].
+    pub number_of_delays_late_aircraft: Option<i64>,
+
+    /// See [Statistics::number_of_delays_national_aviation_system].
+    pub number_of_delays_national_aviation_system: Option<i64>,
+
+    /// See [Statistics::number_of_delays_security].
+    pub number_of_delays_security: Option<i64>,
+
+    /// See [Statistics::number_of_delays_weather].
+    pub number_of_delays_weather: Option<i64>,
+}
+
+impl CsvRecord for UpdateStatistics {}
+
+impl<'a> From<&'a UpdateStatistics> for crate::database::models::UpdateStatistics<'a> {
+    fn from(source: &'a UpdateStatistics) -> Self {
+        Self {
+            flights_cancelled: source.flights_cancelled.as_ref(),
+            flights_delayed: source.flights_delayed.as_ref(),
+            flights_diverted: source.flights_diverted.as_ref(),
+            flights_on_time: source.flights_on_time.as_ref(),
+            minutes_delayed_carrier: source.minutes_delayed_carrier.as_ref(),
+            minutes_delayed_late_aircraft: source.minutes_delayed_late_aircraft.as_ref(),
+            minutes_delayed_national_aviation_system: source
+                .minutes_delayed_national_aviation_system
+                .as_ref(),
+            minutes_delayed_security: source.minutes_delayed_security.as_ref(),
+            minutes_delayed_weather: source.minutes_delayed_weather.as_ref(),
+            number_of_delays_carrier: source.number_of_delays_carrier.as_ref(),
+            number_of_delays_late_aircraft: source.number_of_delays_late_aircraft.as_ref(),
+            number_of_delays_national_aviation_system: source
+                .number_of_delays_national_aviation_system
+                .as_ref(),
+            number_of_delays_security: source.number_of_delays_security.as_ref(),
+            number_of_delays_weather: source.number_of_delays_weather.as_ref(),
+        }
+    }
+}
+
+/// The outcome of inserting a record of a batch of statistics.
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    /// The record was inserted.
+    Created,
+
+    /// The record conflicts with existing statistics for the same carrier,
+    /// airport, and time.
+    Conflict,
+
+    /// The record refers to a carrier or airport which does not exist.
+    Invalid,
+
+    /// The record could not be inserted for another reason.
+    Failed,
+
+    /// The record was inserted, but the insertion was rolled back as another
+    /// record of the batch failed.
+    RolledBack,
+
+    /// The record was not attempted as an earlier record of the batch failed.
+    Skipped,
+}
+
+/// The result of inserting a record of a batch of statistics as returned by the
+/// API.
+#[derive(Serialize)]
+pub struct StatisticsBatchRecord {
+    /// The 1-based position of the record in the batch.
+    pub row: usize,
+
+    /// The outcome of inserting the record.
+    pub status: BatchStatus,
+
+    /// The id of the inserted set of statistics, if it was inserted.
+    pub id: Option<i64>,
+
+    /// A description of the error, if the record could not be inserted.
+    pub error: Option<String>,
+}
+
+/// The result of inserting a batch of statistics as returned by the API.
+#[derive(Serialize)]
+pub struct StatisticsBatch {
+    /// Whether the inserted records have been committed to the database.
+    pub committed: bool,
+
+    /// The number of records inserted.
+    pub created: usize,
+
+    /// The number of records which could not be inserted.
+    pub failed: usize,
+
+    /// The result of each record of the batch.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub records: Vec<StatisticsBatchRecord>,
+}